@@ -1,7 +1,5 @@
 //! Stands for axis-aligned bounding box.
 
-use std::mem;
-
 use crate::ray::Ray;
 use crate::Point3;
 
@@ -20,14 +18,10 @@ impl Aabb {
         let mut t_min = t_min;
         let mut t_max = t_max;
         for a in 0..3 {
-            let inv_d = 1.0 / r.direction[a];
-            let mut t0 = (self.min[a] - r.origin[a]) * inv_d;
-            let mut t1 = (self.max[a] - r.origin[a]) * inv_d;
-            if inv_d < 0.0 {
-                mem::swap(&mut t0, &mut t1);
-            }
-            t_min = t_min.max(t0);
-            t_max = t_max.min(t1);
+            let t0 = (self.min[a] - r.origin[a]) * r.inv_direction[a];
+            let t1 = (self.max[a] - r.origin[a]) * r.inv_direction[a];
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
             if t_max <= t_min {
                 return false;
             }
@@ -35,6 +29,15 @@ impl Aabb {
         true
     }
 
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
     pub fn surrounding(box0: &Self, box1: &Self) -> Self {
         let small = Point3::new(
             box0.min.x.min(box1.min.x),
@@ -0,0 +1,91 @@
+//! Adaptive sampling, which keeps adding samples to the pixels whose
+//! estimates are still noisy and leaves the rest alone.
+
+use std::ops::Range;
+
+use image::{Rgb, RgbImage};
+
+use crate::film::Film;
+
+/// Means darker than this count as this bright when judging relative error,
+/// so nearly black pixels don't chase tiny absolute errors.
+const DARKEST_MEAN: f64 = 1.0 / 64.0;
+
+pub struct Adaptive {
+    /// Samples every pixel takes before its variance is trusted, and how
+    /// many more a noisy pixel takes each round.
+    pub min_samples: usize,
+    pub max_samples: usize,
+    /// Relative standard error of a pixel's mean luminance at which it
+    /// stops.
+    pub threshold: f64,
+}
+
+impl Adaptive {
+    /// The samples each pixel should take next, row by row. A pixel stops
+    /// once it and its eight neighbours are all under the threshold, so a
+    /// pixel that got lucky in a noisy region keeps going with them.
+    pub fn next_samples(&self, film: &Film) -> Vec<Range<usize>> {
+        let (width, height) = (film.width(), film.height());
+        let errors = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| error(film, x, y))
+            .collect::<Vec<_>>();
+        let mut samples = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let n = film.samples(x, y);
+                let converged = (y.saturating_sub(1)..(y + 2).min(height)).all(|ny| {
+                    (x.saturating_sub(1)..(x + 2).min(width))
+                        .all(|nx| errors[ny * width + nx] <= self.threshold)
+                });
+                let end = if n < self.min_samples {
+                    self.min_samples
+                } else if converged {
+                    n
+                } else {
+                    n + self.min_samples
+                };
+                samples.push(n..end.min(self.max_samples).max(n));
+            }
+        }
+        samples
+    }
+}
+
+/// The relative standard error of a pixel's mean luminance.
+fn error(film: &Film, x: usize, y: usize) -> f64 {
+    match film.luminance_stats(x, y) {
+        Some((mean, variance)) => {
+            (variance / film.samples(x, y) as f64).sqrt() / mean.max(DARKEST_MEAN)
+        }
+        None => f64::INFINITY,
+    }
+}
+
+/// Shows how many samples each pixel took, from black for none through
+/// blue, red and yellow to white for the most any pixel took.
+pub fn heatmap(film: &Film) -> RgbImage {
+    const RAMP: [[f32; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [1.0, 1.0, 1.0],
+    ];
+    let most = (0..film.height())
+        .flat_map(|y| (0..film.width()).map(move |x| (x, y)))
+        .map(|(x, y)| film.samples(x, y))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    RgbImage::from_fn(film.width() as u32, film.height() as u32, |x, y| {
+        let t = film.samples(x as usize, y as usize) as f32 / most as f32;
+        let position = t * (RAMP.len() - 1) as f32;
+        let i = (position as usize).min(RAMP.len() - 2);
+        let f = position - i as f32;
+        let [a, b] = [RAMP[i], RAMP[i + 1]];
+        let channel = |c: usize| (255.0 * (a[c] + f * (b[c] - a[c]))).round() as u8;
+        Rgb([channel(0), channel(1), channel(2)])
+    })
+}
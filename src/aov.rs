@@ -0,0 +1,336 @@
+//! Auxiliary output variables: what each pixel's camera rays first hit,
+//! for compositing and for guiding the denoiser.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use exr::prelude::{
+    AnyChannel, AnyChannels, Encoding, FlatSamples, Image, Layer, LayerAttributes, SmallVec,
+    WritableImage,
+};
+use image::{ImageResult, Rgb, RgbImage};
+
+use crate::film::{self, Film};
+use crate::hittable::Hittable;
+use crate::material::Material;
+use crate::rng;
+use crate::tonemap::DisplayTransform;
+use crate::vec3::Vec3;
+use crate::{Color, Point3};
+
+/// Most samples per pixel the AOV pass takes; albedo and normals settle
+/// long before the color does.
+pub const MAX_SAMPLES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AovKind {
+    /// Surface color at the first hit, averaged over the pixel.
+    Albedo,
+    /// Shading normal at the first hit, averaged over the pixel.
+    Normal,
+    /// How far the first sample's hit is in front of the camera, along the
+    /// direction it looks in rather than along the ray.
+    Depth,
+    /// World-space position of the first sample's hit.
+    Position,
+    /// How many pixels the first sample's hit moves across the image while
+    /// the shutter is open.
+    Motion,
+    /// Which of the scene's top-level objects the first sample hit,
+    /// counting from 1.
+    ObjectId,
+    /// Which material the first sample hit, numbered from 1 in the order
+    /// the scene was built with them.
+    MaterialId,
+}
+
+impl AovKind {
+    fn name(self) -> &'static str {
+        match self {
+            AovKind::Albedo => "albedo",
+            AovKind::Normal => "normal",
+            AovKind::Depth => "depth",
+            AovKind::Position => "position",
+            AovKind::Motion => "motion",
+            AovKind::ObjectId => "object_id",
+            AovKind::MaterialId => "material_id",
+        }
+    }
+
+    fn channels(self) -> &'static [&'static str] {
+        match self {
+            AovKind::Albedo => &["R", "G", "B"],
+            AovKind::Normal | AovKind::Position => &["X", "Y", "Z"],
+            AovKind::Depth => &["Z"],
+            AovKind::Motion => &["X", "Y"],
+            AovKind::ObjectId | AovKind::MaterialId => &["id"],
+        }
+    }
+}
+
+/// What one camera ray hit.
+pub struct Hit {
+    pub albedo: Color,
+    pub normal: Vec3,
+    pub depth: f32,
+    pub position: Point3,
+    /// In pixels, with y pointing down the image.
+    pub motion: [f32; 2],
+    pub object_id: u32,
+    pub material: u32,
+}
+
+/// The buffers of one pixel. Albedo and normal are sums over every sample;
+/// the rest come from the first sample alone, since averaging depths or IDs
+/// across an edge gives values that belong to neither side.
+#[derive(Debug, Clone, Copy)]
+pub struct AovPixel {
+    albedo: Color,
+    normal: Vec3,
+    samples: u32,
+    depth: f32,
+    position: Point3,
+    motion: [f32; 2],
+    object_id: u32,
+    material: u32,
+}
+
+impl Default for AovPixel {
+    fn default() -> Self {
+        Self {
+            albedo: Color::zeros(),
+            normal: Vec3::zeros(),
+            samples: 0,
+            depth: f32::INFINITY,
+            position: Point3::zeros(),
+            motion: [0.0; 2],
+            object_id: 0,
+            material: 0,
+        }
+    }
+}
+
+impl AovPixel {
+    /// Adds a sample that hit `hit`, or nothing.
+    pub fn add(&mut self, hit: Option<&Hit>) {
+        let first = self.samples == 0;
+        self.samples += 1;
+        let Some(hit) = hit else { return };
+        self.albedo += hit.albedo;
+        self.normal += hit.normal;
+        if first {
+            self.depth = hit.depth;
+            self.position = hit.position;
+            self.motion = hit.motion;
+            self.object_id = hit.object_id;
+            self.material = hit.material;
+        }
+    }
+}
+
+/// Numbers the materials of a scene from 1, once, so a material keeps its
+/// ID in every image of the scene.
+pub struct MaterialIds(HashMap<usize, u32>);
+
+impl MaterialIds {
+    pub fn new(world: &dyn Hittable) -> Self {
+        let mut ids = HashMap::new();
+        world.materials(&mut |material| {
+            let next = ids.len() as u32 + 1;
+            ids.entry(address(material)).or_insert(next);
+        });
+        Self(ids)
+    }
+
+    /// The ID of `material`, or 0 if it isn't part of the scene.
+    pub fn get(&self, material: &Arc<dyn Material>) -> u32 {
+        self.0.get(&address(material)).copied().unwrap_or(0)
+    }
+}
+
+fn address(material: &Arc<dyn Material>) -> usize {
+    Arc::as_ptr(material) as *const () as usize
+}
+
+pub struct Aovs {
+    width: usize,
+    height: usize,
+    pixels: Vec<AovPixel>,
+}
+
+impl Aovs {
+    /// Takes the pixels a row at a time from the top.
+    pub fn new(width: usize, height: usize, pixels: Vec<AovPixel>) -> Self {
+        assert_eq!(pixels.len(), width * height);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The mean albedo of a pixel's samples, black where they missed.
+    pub fn albedo(&self, x: usize, y: usize) -> Color {
+        let pixel = &self.pixels[y * self.width + x];
+        pixel.albedo / pixel.samples.max(1) as f32
+    }
+
+    /// The mean of a pixel's shading normals, which is shorter than 1 where
+    /// the samples disagree and zero where they all missed.
+    pub fn normal(&self, x: usize, y: usize) -> Vec3 {
+        let pixel = &self.pixels[y * self.width + x];
+        pixel.normal / pixel.samples.max(1) as f32
+    }
+
+    /// The depth of a pixel's first sample's hit, infinite if it missed.
+    pub fn depth(&self, x: usize, y: usize) -> f32 {
+        self.pixels[y * self.width + x].depth
+    }
+
+    /// The raw values of one buffer at a pixel, padded with zeros to three
+    /// channels.
+    fn values(&self, kind: AovKind, x: usize, y: usize) -> [f32; 3] {
+        let pixel = &self.pixels[y * self.width + x];
+        match kind {
+            AovKind::Albedo => (*self.albedo(x, y)).into(),
+            AovKind::Normal => (*self.normal(x, y)).into(),
+            AovKind::Depth => [pixel.depth, 0.0, 0.0],
+            AovKind::Position => (*pixel.position).into(),
+            AovKind::Motion => [pixel.motion[0], pixel.motion[1], 0.0],
+            AovKind::ObjectId => [pixel.object_id as f32, 0.0, 0.0],
+            AovKind::MaterialId => [pixel.material as f32, 0.0, 0.0],
+        }
+    }
+
+    /// Writes `film` to `path` along with the buffers in `kinds`. An EXR
+    /// gets them as extra layers of the same file; any other format gets a
+    /// file per buffer named after `path`, such as `out.normal.png`, with
+    /// 8-bit formats showing each buffer scaled into a visible range.
+    pub fn save(
+        &self,
+        kinds: &[AovKind],
+        film: &Film,
+        path: &Path,
+        display: &DisplayTransform,
+    ) -> ImageResult<()> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        if extension == "exr" {
+            return Ok(self.write_exr(kinds, film, path)?);
+        }
+        film.save(path, display)?;
+        for &kind in kinds {
+            let path = film::tagged_path(path, kind.name());
+            if matches!(extension.as_str(), "hdr" | "pfm" | "film") {
+                let mut layer = Film::new(self.width, self.height);
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        layer.add(x, y, self.values(kind, x, y).into(), 1.0);
+                    }
+                }
+                layer.save(&path, display)?;
+            } else {
+                self.visualize(kind).save(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_exr(&self, kinds: &[AovKind], film: &Film, path: &Path) -> io::Result<()> {
+        let channel = |name: String, value: &dyn Fn(usize, usize) -> f32| {
+            let samples = (0..self.height)
+                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                .map(|(x, y)| value(x, y))
+                .collect();
+            AnyChannel::new(name.as_str(), FlatSamples::F32(samples))
+        };
+        let mut channels = Vec::new();
+        for (c, name) in ["R", "G", "B"].into_iter().enumerate() {
+            channels.push(channel(name.to_string(), &|x, y| film.color(x, y)[c]));
+        }
+        for &kind in kinds {
+            for (c, name) in kind.channels().iter().enumerate() {
+                channels.push(channel(format!("{}.{name}", kind.name()), &|x, y| {
+                    self.values(kind, x, y)[c]
+                }));
+            }
+        }
+        let layer = Layer::new(
+            (self.width, self.height),
+            LayerAttributes::default(),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(SmallVec::from_vec(channels)),
+        );
+        Image::from_layer(layer)
+            .write()
+            .to_file(path)
+            .map_err(|e| io::Error::other(e.to_string()))
+    }
+
+    /// Maps a buffer to colors that show its structure.
+    fn visualize(&self, kind: AovKind) -> RgbImage {
+        let hits = || self.pixels.iter().filter(|p| p.depth.is_finite());
+        let max_depth = hits().map(|p| p.depth).fold(0.0, f32::max);
+        let (min_position, max_position) = hits().fold(
+            (
+                Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+                Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |(min, max), p| (min.inf(&p.position).into(), max.sup(&p.position).into()),
+        );
+        let max_motion = self
+            .pixels
+            .iter()
+            .map(|p| p.motion[0].hypot(p.motion[1]))
+            .fold(0.0, f32::max);
+
+        RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let (x, y) = (x as usize, y as usize);
+            let pixel = &self.pixels[y * self.width + x];
+            let [r, g, b] = self.values(kind, x, y);
+            let color = match kind {
+                AovKind::Albedo => [r, g, b],
+                AovKind::Normal => [0.5 * r + 0.5, 0.5 * g + 0.5, 0.5 * b + 0.5],
+                AovKind::Depth if pixel.depth.is_finite() => [1.0 - r / max_depth; 3],
+                AovKind::Depth => [0.0; 3],
+                AovKind::Position if pixel.depth.is_finite() => {
+                    let extent = max_position - min_position;
+                    let scaled = |c: usize| (pixel.position[c] - min_position[c]) / extent[c];
+                    [scaled(0), scaled(1), scaled(2)]
+                }
+                AovKind::Position => [0.0; 3],
+                AovKind::Motion if max_motion > 0.0 => {
+                    [0.5 + 0.5 * r / max_motion, 0.5 + 0.5 * g / max_motion, 0.5]
+                }
+                AovKind::Motion => [0.5, 0.5, 0.5],
+                AovKind::ObjectId => id_color(pixel.object_id as u64),
+                AovKind::MaterialId => id_color(pixel.material as u64),
+            };
+            let encode = |c: f32| (255.0 * c.clamp(0.0, 1.0)).round() as u8;
+            Rgb([encode(color[0]), encode(color[1]), encode(color[2])])
+        })
+    }
+}
+
+/// A bright color picked at random for each ID, and black for none.
+fn id_color(id: u64) -> [f32; 3] {
+    if id == 0 {
+        return [0.0; 3];
+    }
+    let bits = rng::mix(id);
+    let channel = |shift: u32| 0.25 + 0.75 * ((bits >> shift) & 0xff) as f32 / 255.0;
+    [channel(0), channel(8), channel(16)]
+}
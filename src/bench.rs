@@ -0,0 +1,85 @@
+//! Compares ray throughput of the recursive `BvhNode` and the flattened
+//! `LinearBvh` built from the same scene, against the tree the original
+//! builder made: one object per leaf, split along random axes.
+
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::camera::Camera;
+use crate::hittable::bvh_node::{BvhNode, BvhOptions, SplitMethod};
+use crate::hittable::hittable_list::HittableList;
+use crate::hittable::linear_bvh::LinearBvh;
+use crate::hittable::Hittable;
+use crate::random_unit_vector;
+use crate::ray::Ray;
+use crate::rng;
+
+const PASSES: usize = 5;
+
+pub fn run(
+    camera: &Camera,
+    world: &HittableList,
+    options: &BvhOptions,
+    image_width: usize,
+    image_height: usize,
+) {
+    let original_options = BvhOptions {
+        split: SplitMethod::Random,
+        max_leaf_size: 1,
+        ..options.clone()
+    };
+    let original = BvhNode::with_options(world.children(), 0.0, 1.0, &original_options);
+    let tree = BvhNode::with_options(world.children(), 0.0, 1.0, options);
+    let linear = LinearBvh::from(BvhNode::with_options(world.children(), 0.0, 1.0, options));
+
+    // One jittered camera ray per pixel, plus a diffuse bounce from wherever
+    // it lands so the set isn't all coherent primary rays.
+    let mut rng = rng::rng();
+    let mut rays = Vec::with_capacity(2 * image_width * image_height);
+    for j in 0..image_height {
+        for i in 0..image_width {
+            let u = (i as f32 + rng.gen_range(0.0..1.0)) / image_width as f32;
+            let v = (j as f32 + rng.gen_range(0.0..1.0)) / image_height as f32;
+            rays.push(camera.get_ray(u, v));
+        }
+    }
+    let secondary = rays
+        .iter()
+        .filter_map(|r| {
+            let rec = tree.hit(r, 0.001, f32::INFINITY)?;
+            Some(Ray::new(rec.p, rec.normal + random_unit_vector(), r.time))
+        })
+        .collect::<Vec<_>>();
+    rays.extend(secondary);
+
+    println!("Tracing {} rays, best of {PASSES} passes", rays.len());
+    let original_time = time(&original, &rays);
+    for (name, elapsed) in [
+        ("Original", original_time),
+        ("BvhNode", time(&tree, &rays)),
+        ("LinearBvh", time(&linear, &rays)),
+    ] {
+        println!(
+            "{name:>10}: {:.3} s, {:.2} Mrays/s, {:.2}x the original",
+            elapsed.as_secs_f32(),
+            rays.len() as f32 / elapsed.as_secs_f32() / 1e6,
+            original_time.as_secs_f32() / elapsed.as_secs_f32()
+        );
+    }
+}
+
+fn time(world: &dyn Hittable, rays: &[Ray]) -> Duration {
+    (0..PASSES)
+        .map(|_| {
+            let start = Instant::now();
+            let hits = rays
+                .iter()
+                .filter(|r| world.hit(r, 0.001, f32::INFINITY).is_some())
+                .count();
+            std::hint::black_box(hits);
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
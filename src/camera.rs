@@ -1,6 +1,5 @@
-use crate::random_vec_in_unit_disk;
+use crate::sampler;
 use crate::{ray::Ray, Point3, Vec3};
-use rand::Rng;
 
 pub struct Camera {
     origin: Point3,
@@ -58,13 +57,42 @@ impl Camera {
     }
 
     pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let rd = self.lens_radius * random_vec_in_unit_disk();
-        let offset = self.u * rd.x + self.v * rd.y;
+        let [dx, dy] = sampler::to_unit_disk(sampler::next_2d());
+        let offset = self.u * (self.lens_radius * dx) + self.v * (self.lens_radius * dy);
 
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
-            rand::thread_rng().gen_range(self.time0..self.time1),
+            self.time0 + sampler::next_1d() * (self.time1 - self.time0),
         )
     }
+
+    /// Where `p` lands on the image, as the `s` and `t` `get_ray` takes,
+    /// seen through the center of the lens; `None` if it's behind the
+    /// camera.
+    pub fn project(&self, p: Point3) -> Option<[f32; 2]> {
+        let depth = self.depth(p);
+        if depth <= 0.0 {
+            return None;
+        }
+        let focus_dist = self.depth(self.lower_left_corner);
+        let offset =
+            self.origin + (p - self.origin) * (focus_dist / depth) - self.lower_left_corner;
+        Some([
+            offset.dot(&self.horizontal) / self.horizontal.norm_squared(),
+            offset.dot(&self.vertical) / self.vertical.norm_squared(),
+        ])
+    }
+
+    /// How far `p` is in front of the camera, measured along the direction
+    /// it looks in, so every point of a plane facing the camera has the same
+    /// depth wherever on the lens a ray left from.
+    pub fn depth(&self, p: Point3) -> f32 {
+        -(p - self.origin).dot(&self.w)
+    }
+
+    /// The times the shutter opens and closes.
+    pub fn shutter(&self) -> (f32, f32) {
+        (self.time0, self.time1)
+    }
 }
@@ -1,40 +1,15 @@
-use image::RgbImage;
-
 use crate::Color;
 
-pub fn write_color(
-    out: &mut RgbImage,
-    pos: (usize, usize),
-    pixel_color: &Color,
-    samples_per_pixel: usize,
-) {
-    let mut r = pixel_color.x;
-    let mut g = pixel_color.y;
-    let mut b = pixel_color.z;
+/// Relative luminance of a linear Rec. 709 color.
+pub fn luminance(color: &Color) -> f32 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
 
-    if r.is_nan() {
-        r = 0.0;
-    }
-    if g.is_nan() {
-        g = 0.0;
-    }
-    if b.is_nan() {
-        b = 0.0;
+/// The sRGB transfer curve, for a channel in [0, 1].
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
     }
-
-    let scale = 1.0 / samples_per_pixel as f32;
-
-    r = (scale * r).sqrt();
-    g = (scale * g).sqrt();
-    b = (scale * b).sqrt();
-
-    out.put_pixel(
-        pos.0 as u32,
-        pos.1 as u32,
-        image::Rgb([
-            (256.0 * r.clamp(0.0, 0.999)) as u8,
-            (256.0 * g.clamp(0.0, 0.999)) as u8,
-            (256.0 * b.clamp(0.0, 0.999)) as u8,
-        ]),
-    );
 }
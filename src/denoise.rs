@@ -0,0 +1,201 @@
+//! A denoiser for finished renders: the edge-avoiding à-trous wavelet filter
+//! of Dammertz et al., with the variance-guided luminance weights of
+//! Schied et al.'s SVGF. It smooths the lighting with ever wider sparse
+//! kernels, and stops at the edges the albedo, normal and depth buffers show.
+
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::aov::Aovs;
+use crate::color::luminance;
+use crate::film::Film;
+use crate::vec3::Vec3;
+use crate::Color;
+
+/// Passes of the filter, each with taps twice as far apart as the last, so
+/// the widest reaches 2 * (2^5 - 1) = 62 pixels.
+const ITERATIONS: usize = 5;
+
+/// The B3 spline weights of taps 0, 1 and 2 steps from the center.
+const KERNEL: [f32; 3] = [3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// How many standard deviations of noise two luminances may differ by
+/// before they stop counting as the same lighting.
+const SIGMA_LUMINANCE: f32 = 4.0;
+
+/// The exponent on the cosine between two normals; higher keeps creases
+/// sharper.
+const NORMAL_POWER: f32 = 128.0;
+
+/// How far two depths may differ, in units of the depth gradient times the
+/// distance between the pixels.
+const SIGMA_DEPTH: f32 = 1.0;
+
+/// Albedo channels darker than this are left out of the demodulation, which
+/// would otherwise blow their noise up.
+const MIN_ALBEDO: f32 = 1e-3;
+
+/// What the filter knows about the surface a pixel shows.
+struct Guide {
+    /// Unit length, or zero where the pixel's samples all missed.
+    normal: Vec3,
+    depth: f32,
+    /// How fast the depth changes per pixel across the image here.
+    depth_gradient: f32,
+}
+
+/// Returns a denoised copy of `film`, guided by `aovs` of the same size.
+///
+/// The film is divided by the albedo first, so the filter only ever blurs
+/// lighting and multiplying the albedo back in restores texture detail.
+pub fn denoise(film: &Film, aovs: &Aovs) -> Film {
+    let (width, height) = (film.width(), film.height());
+    assert_eq!((aovs.width(), aovs.height()), (width, height));
+    let coordinates = || (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)));
+
+    let albedo = coordinates()
+        .map(|(x, y)| {
+            aovs.albedo(x, y)
+                .map(|c| if c > MIN_ALBEDO { c } else { 1.0 })
+                .into()
+        })
+        .collect::<Vec<Color>>();
+    let mut illumination = coordinates()
+        .zip(&albedo)
+        .map(|((x, y), albedo)| film.color(x, y).component_div(albedo).into())
+        .collect::<Vec<Color>>();
+    // The variance of each pixel's mean luminance, scaled like the pixel by
+    // the demodulation. Pixels with too few samples to tell get an infinite
+    // one, which lets them blend with anything.
+    let mut variance = coordinates()
+        .zip(&albedo)
+        .map(|((x, y), albedo)| match film.luminance_stats(x, y) {
+            Some((_, variance)) => {
+                variance as f32 / film.samples(x, y) as f32 / luminance(albedo).powi(2)
+            }
+            None => f32::INFINITY,
+        })
+        .collect::<Vec<f32>>();
+
+    let depth = |x: usize, y: usize| aovs.depth(x, y);
+    let guides = coordinates()
+        .map(|(x, y)| {
+            let normal = aovs.normal(x, y);
+            // The smaller one-sided difference along each axis, so a pixel
+            // beside an edge doesn't take the jump across it as its slope.
+            let slope = |previous: Option<f32>, next: Option<f32>| {
+                [previous, next]
+                    .into_iter()
+                    .flatten()
+                    .map(|z| (z - depth(x, y)).abs())
+                    .filter(|d| d.is_finite())
+                    .fold(f32::INFINITY, f32::min)
+            };
+            let dx = slope(
+                x.checked_sub(1).map(|x| depth(x, y)),
+                (x + 1 < width).then(|| depth(x + 1, y)),
+            );
+            let dy = slope(
+                y.checked_sub(1).map(|y| depth(x, y)),
+                (y + 1 < height).then(|| depth(x, y + 1)),
+            );
+            Guide {
+                normal: if normal.norm() > 0.0 {
+                    normal.normalize()
+                } else {
+                    normal
+                },
+                depth: depth(x, y),
+                depth_gradient: [dx, dy].into_iter().filter(|d| d.is_finite()).sum(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    for iteration in 0..ITERATIONS {
+        let step = 1 << iteration;
+        let deviation = blur(&variance, width, height)
+            .into_iter()
+            .map(f32::sqrt)
+            .collect::<Vec<_>>();
+        let filtered = (0..height)
+            .into_par_iter()
+            .flat_map_iter(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let p = y * width + x;
+                let l = luminance(&illumination[p]);
+                let mut sum = Color::zeros();
+                let mut weights = 0.0;
+                let mut variance_sum = 0.0;
+                for dy in -2..=2_isize {
+                    for dx in -2..=2_isize {
+                        let qx = x as isize + dx * step;
+                        let qy = y as isize + dy * step;
+                        if qx < 0 || qy < 0 || qx >= width as isize || qy >= height as isize {
+                            continue;
+                        }
+                        let q = qy as usize * width + qx as usize;
+                        let distance = step as f32 * ((dx * dx + dy * dy) as f32).sqrt();
+                        let lighting = (-(l - luminance(&illumination[q])).abs()
+                            / (SIGMA_LUMINANCE * deviation[p] + 1e-6))
+                            .exp();
+                        let weight = KERNEL[dx.unsigned_abs()]
+                            * KERNEL[dy.unsigned_abs()]
+                            * lighting
+                            * similarity(&guides[p], &guides[q], distance);
+                        if weight == 0.0 {
+                            continue;
+                        }
+                        sum += illumination[q] * weight;
+                        weights += weight;
+                        variance_sum += weight * weight * variance[q];
+                    }
+                }
+                // The center tap always has a weight, so `weights` isn't 0.
+                (sum / weights, variance_sum / (weights * weights))
+            })
+            .collect::<Vec<_>>();
+        (illumination, variance) = filtered.into_iter().unzip();
+    }
+
+    let mut denoised = Film::new(width, height);
+    for ((x, y), (illumination, albedo)) in coordinates().zip(illumination.iter().zip(&albedo)) {
+        denoised.add(x, y, illumination.component_mul(albedo).into(), 1.0);
+    }
+    denoised
+}
+
+/// How much two pixels `distance` apart look like the same surface, from 0
+/// to 1.
+fn similarity(p: &Guide, q: &Guide, distance: f32) -> f32 {
+    let (p_hit, q_hit) = (p.depth.is_finite(), q.depth.is_finite());
+    if !p_hit || !q_hit {
+        // The background only blends with itself.
+        return if p_hit == q_hit { 1.0 } else { 0.0 };
+    }
+    let normal = p.normal.dot(&q.normal).max(0.0).powf(NORMAL_POWER);
+    let depth = (-(p.depth - q.depth).abs()
+        / (SIGMA_DEPTH * p.depth_gradient * distance + 1e-3 * p.depth))
+        .exp();
+    normal * depth
+}
+
+/// A 3x3 Gaussian blur of one channel, to steady the variance estimates the
+/// luminance weights are scaled by.
+fn blur(values: &[f32], width: usize, height: usize) -> Vec<f32> {
+    const WEIGHTS: [f32; 2] = [1.0 / 2.0, 1.0 / 4.0];
+    let mut blurred = Vec::with_capacity(values.len());
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = 0.0;
+            let mut total = 0.0;
+            for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                    let weight = WEIGHTS[nx.abs_diff(x)] * WEIGHTS[ny.abs_diff(y)];
+                    sum += weight * values[ny * width + nx];
+                    total += weight;
+                }
+            }
+            blurred.push(sum / total);
+        }
+    }
+    blurred
+}
@@ -0,0 +1,377 @@
+//! The floating-point image a render accumulates into, and writers for it.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use image::codecs::hdr::HdrEncoder;
+use image::{ImageResult, Rgb, Rgb32FImage, RgbImage};
+
+use crate::color::luminance;
+use crate::render::RenderedTile;
+use crate::tonemap::DisplayTransform;
+use crate::Color;
+
+/// Identifies a checkpoint file and its layout version.
+const CHECKPOINT_MAGIC: &[u8; 8] = b"RTWFILM6";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Pixel {
+    sum: Color,
+    weight: f32,
+    /// Samples taken through this pixel, wherever the filter spread them.
+    samples: u32,
+    /// Sums of those samples' luminance and of its square.
+    luminance: f64,
+    luminance_sq: f64,
+}
+
+/// What a render was started with, kept in its checkpoints so resuming and
+/// merging can check they are combining samples of the same render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSettings {
+    /// The built-in scene's name, or the path of the scene file.
+    pub scene: String,
+    /// The `--seed` of every render in the film: one, unless it was merged.
+    pub seeds: Vec<u64>,
+    pub sampler: String,
+    pub filter: String,
+    pub filter_radius: f32,
+    /// Summed over the renders in the film.
+    pub samples_per_pixel: u32,
+}
+
+/// Filter-weighted sums of radiance samples per pixel, with row 0 at the
+/// top.
+#[derive(PartialEq)]
+pub struct Film {
+    width: usize,
+    height: usize,
+    pixels: Vec<Pixel>,
+    /// Set for films a render fills, and saved with them.
+    settings: Option<RenderSettings>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![
+                Pixel {
+                    sum: Color::zeros(),
+                    weight: 0.0,
+                    samples: 0,
+                    luminance: 0.0,
+                    luminance_sq: 0.0,
+                };
+                width * height
+            ],
+            settings: None,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn settings(&self) -> Option<&RenderSettings> {
+        self.settings.as_ref()
+    }
+
+    pub fn set_settings(&mut self, settings: RenderSettings) {
+        self.settings = Some(settings);
+    }
+
+    /// Adds radiance `sum`, already multiplied by filter weights that total
+    /// `weight`.
+    pub fn add(&mut self, x: usize, y: usize, sum: Color, weight: f32) {
+        let pixel = &mut self.pixels[y * self.width + x];
+        pixel.sum += sum;
+        pixel.weight += weight;
+    }
+
+    /// Discards every sample, as when the camera moves.
+    pub fn clear(&mut self) {
+        for pixel in &mut self.pixels {
+            pixel.sum = Color::zeros();
+            pixel.weight = 0.0;
+            pixel.samples = 0;
+            pixel.luminance = 0.0;
+            pixel.luminance_sq = 0.0;
+        }
+    }
+
+    /// Adds the splats of one tile from a render pass.
+    pub fn add_tile(&mut self, tile: &RenderedTile) {
+        for ((x, y), (sum, weight)) in tile
+            .bounds
+            .pixels()
+            .zip(tile.sums.iter().zip(&tile.weights))
+        {
+            self.add(x, y, *sum, *weight);
+        }
+        for (i, (x, y)) in tile.tile.pixels().enumerate() {
+            let pixel = &mut self.pixels[y * self.width + x];
+            pixel.samples += tile.samples[i] as u32;
+            pixel.luminance += tile.luminance[i];
+            pixel.luminance_sq += tile.luminance_sq[i];
+        }
+    }
+
+    /// Adds all of `other`'s samples, which must be the same size. The
+    /// settings gain `other`'s seeds and samples per pixel, and are lost if
+    /// either film has none.
+    pub fn add_film(&mut self, other: &Film) {
+        assert_eq!((self.width, self.height), (other.width, other.height));
+        self.settings = match (self.settings.take(), &other.settings) {
+            (Some(mut settings), Some(other)) => {
+                settings.seeds.extend(&other.seeds);
+                settings.samples_per_pixel += other.samples_per_pixel;
+                Some(settings)
+            }
+            _ => None,
+        };
+        for (pixel, other) in self.pixels.iter_mut().zip(&other.pixels) {
+            pixel.sum += other.sum;
+            pixel.weight += other.weight;
+            pixel.samples += other.samples;
+            pixel.luminance += other.luminance;
+            pixel.luminance_sq += other.luminance_sq;
+        }
+    }
+
+    /// How many samples have been taken through a pixel.
+    pub fn samples(&self, x: usize, y: usize) -> usize {
+        self.pixels[y * self.width + x].samples as usize
+    }
+
+    /// The mean and sample variance of the luminance of the samples taken
+    /// through a pixel, or `None` with fewer than two.
+    pub fn luminance_stats(&self, x: usize, y: usize) -> Option<(f64, f64)> {
+        let pixel = &self.pixels[y * self.width + x];
+        if pixel.samples < 2 {
+            return None;
+        }
+        let n = pixel.samples as f64;
+        let mean = pixel.luminance / n;
+        let variance = (pixel.luminance_sq - n * mean * mean) / (n - 1.0);
+        Some((mean, variance.max(0.0)))
+    }
+
+    /// The filtered radiance of a pixel, or black if no samples reach it.
+    pub fn color(&self, x: usize, y: usize) -> Color {
+        let pixel = &self.pixels[y * self.width + x];
+        if pixel.weight > 0.0 {
+            pixel.sum / pixel.weight
+        } else {
+            Color::zeros()
+        }
+    }
+
+    pub fn to_rgb_image(&self, display: &DisplayTransform) -> RgbImage {
+        let white_point = display.white_point.unwrap_or_else(|| {
+            let max = (0..self.height)
+                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                .map(|(x, y)| luminance(&self.color(x, y)))
+                .fold(0.0, f32::max);
+            max * display.exposure.exp2()
+        });
+        RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            display.apply(&self.color(x as usize, y as usize), white_point)
+        })
+    }
+
+    fn to_rgb32f_image(&self) -> Rgb32FImage {
+        Rgb32FImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let c = self.color(x as usize, y as usize);
+            Rgb([c.x, c.y, c.z])
+        })
+    }
+
+    /// Writes the film in the format named by the extension of `path`:
+    /// `.exr`, `.hdr` and `.pfm` keep the linear radiance, `.film` is a
+    /// checkpoint of the unclamped sums and sample counts, and anything else
+    /// is tone mapped to 8 bits with `display` and handed to the `image`
+    /// crate.
+    pub fn save(&self, path: &Path, display: &DisplayTransform) -> ImageResult<()> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "exr" => self.to_rgb32f_image().save(path),
+            "hdr" => {
+                let pixels = self.to_rgb32f_image().pixels().copied().collect::<Vec<_>>();
+                HdrEncoder::new(BufWriter::new(File::create(path)?)).encode(
+                    &pixels,
+                    self.width,
+                    self.height,
+                )
+            }
+            "pfm" => Ok(self.write_pfm(path)?),
+            "film" => Ok(self.save_checkpoint(path)?),
+            _ => self.to_rgb_image(display).save(path),
+        }
+    }
+
+    /// Writes the settings, raw sums, weights, sample counts and luminance
+    /// moments so a render can be resumed. The file is written to `path`
+    /// with `.partial` appended and renamed over it, so a crash mid-write
+    /// leaves the previous checkpoint intact.
+    pub fn save_checkpoint(&self, path: &Path) -> io::Result<()> {
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let mut out = BufWriter::new(File::create(&partial)?);
+        out.write_all(CHECKPOINT_MAGIC)?;
+        out.write_all(&(self.width as u64).to_le_bytes())?;
+        out.write_all(&(self.height as u64).to_le_bytes())?;
+        out.write_all(&[self.settings.is_some() as u8])?;
+        if let Some(settings) = &self.settings {
+            write_string(&mut out, &settings.scene)?;
+            out.write_all(&(settings.seeds.len() as u64).to_le_bytes())?;
+            for seed in &settings.seeds {
+                out.write_all(&seed.to_le_bytes())?;
+            }
+            write_string(&mut out, &settings.sampler)?;
+            write_string(&mut out, &settings.filter)?;
+            out.write_all(&settings.filter_radius.to_le_bytes())?;
+            out.write_all(&settings.samples_per_pixel.to_le_bytes())?;
+        }
+        for pixel in &self.pixels {
+            for value in [pixel.sum.x, pixel.sum.y, pixel.sum.z, pixel.weight] {
+                out.write_all(&value.to_le_bytes())?;
+            }
+            out.write_all(&pixel.samples.to_le_bytes())?;
+            out.write_all(&pixel.luminance.to_le_bytes())?;
+            out.write_all(&pixel.luminance_sq.to_le_bytes())?;
+        }
+        out.into_inner()?.sync_all()?;
+        std::fs::rename(partial, path)
+    }
+
+    /// Reads a film written by `save_checkpoint`.
+    pub fn load_checkpoint(path: &Path) -> io::Result<Self> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut magic = [0; CHECKPOINT_MAGIC.len()];
+        input.read_exact(&mut magic)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a render checkpoint",
+            ));
+        }
+        let width = u64::from_le_bytes(read_array(&mut input)?) as usize;
+        let height = u64::from_le_bytes(read_array(&mut input)?) as usize;
+        let mut film = Self::new(width, height);
+        if read_array::<1>(&mut input)? != [0] {
+            film.settings = Some(RenderSettings {
+                scene: read_string(&mut input)?,
+                seeds: (0..u64::from_le_bytes(read_array(&mut input)?))
+                    .map(|_| read_array(&mut input).map(u64::from_le_bytes))
+                    .collect::<io::Result<_>>()?,
+                sampler: read_string(&mut input)?,
+                filter: read_string(&mut input)?,
+                filter_radius: f32::from_le_bytes(read_array(&mut input)?),
+                samples_per_pixel: u32::from_le_bytes(read_array(&mut input)?),
+            });
+        }
+        for pixel in &mut film.pixels {
+            let mut read_f32 = || read_array(&mut input).map(f32::from_le_bytes);
+            pixel.sum = Color::new(read_f32()?, read_f32()?, read_f32()?);
+            pixel.weight = read_f32()?;
+            pixel.samples = u32::from_le_bytes(read_array(&mut input)?);
+            pixel.luminance = f64::from_le_bytes(read_array(&mut input)?);
+            pixel.luminance_sq = f64::from_le_bytes(read_array(&mut input)?);
+        }
+        Ok(film)
+    }
+
+    /// Portable float map: a text header, then little-endian floats with
+    /// the bottom row first.
+    fn write_pfm(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "PF\n{} {}\n-1.0\n", self.width, self.height)?;
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                let c = self.color(x, y);
+                for channel in [c.x, c.y, c.z] {
+                    out.write_all(&channel.to_le_bytes())?;
+                }
+            }
+        }
+        out.flush()
+    }
+}
+
+/// The path of a file saved beside `path` with `tag` before its extension,
+/// such as `out.noisy.png` for `out.png`, or `out.noisy` for `out`.
+pub fn tagged_path(path: &Path, tag: &str) -> PathBuf {
+    match path.extension() {
+        Some(extension) => path.with_extension(format!("{tag}.{}", extension.to_string_lossy())),
+        None => path.with_extension(tag),
+    }
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Writes `s` as its length in bytes followed by its UTF-8.
+fn write_string(out: &mut impl Write, s: &str) -> io::Result<()> {
+    out.write_all(&(s.len() as u64).to_le_bytes())?;
+    out.write_all(s.as_bytes())
+}
+
+fn read_string(input: &mut impl Read) -> io::Result<String> {
+    let len = u64::from_le_bytes(read_array(input)?);
+    let mut bytes = Vec::new();
+    input.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Holds back the tiles of a pass that finish ahead of their turn, so they
+/// are added to the film in the renderer's tile order. Tiles splat into
+/// each other's borders, and `f32` sums taken in whatever order the threads
+/// finish would round differently from run to run.
+pub struct TileQueue {
+    tiles: usize,
+    next: usize,
+    waiting: BTreeMap<usize, RenderedTile>,
+}
+
+impl TileQueue {
+    /// A queue for passes of `tiles` tiles each.
+    pub fn new(tiles: usize) -> Self {
+        Self {
+            tiles,
+            next: 0,
+            waiting: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `tile` to `film` along with any waiting tiles that follow it,
+    /// if it is next in line, and returns how many were added. After the
+    /// last tile of a pass the queue expects the first of the next.
+    pub fn push(&mut self, film: &mut Film, tile: RenderedTile) -> usize {
+        self.waiting.insert(tile.index, tile);
+        let mut added = 0;
+        while let Some(tile) = self.waiting.remove(&self.next) {
+            film.add_tile(&tile);
+            added += 1;
+            self.next = (self.next + 1) % self.tiles;
+        }
+        added
+    }
+}
@@ -0,0 +1,103 @@
+//! Pixel reconstruction filters, which weight each sample's contribution to
+//! the pixels around it.
+
+use std::f32::consts::PI;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FilterKind {
+    /// Equal weight everywhere within the radius.
+    Box,
+    /// Weight falling off linearly to zero at the radius.
+    Tent,
+    /// A Gaussian with a standard deviation of a third of the radius,
+    /// shifted down to reach zero at the radius.
+    Gaussian,
+    /// Mitchell and Netravali's cubic with B = C = 1/3.
+    Mitchell,
+    /// A sinc windowed by a sinc stretched to the radius.
+    Lanczos,
+}
+
+impl FilterKind {
+    pub fn default_radius(self) -> f32 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.0,
+            FilterKind::Lanczos => 2.0,
+        }
+    }
+}
+
+/// A separable filter: a sample `(dx, dy)` pixels from a pixel's center
+/// contributes to it with weight `evaluate(dx) * evaluate(dy)`.
+#[derive(Debug, Clone, Copy)]
+pub struct Filter {
+    pub kind: FilterKind,
+    /// Distance in pixels beyond which the weight is zero.
+    pub radius: f32,
+}
+
+impl Filter {
+    /// The weight at `d` pixels from the center, where `d` is within the
+    /// radius. Mitchell and Lanczos go negative away from the center.
+    pub fn evaluate(&self, d: f32) -> f32 {
+        let r = self.radius;
+        let x = d.abs();
+        match self.kind {
+            FilterKind::Box => 1.0,
+            FilterKind::Tent => (1.0 - x / r).max(0.0),
+            FilterKind::Gaussian => {
+                let sigma = r / 3.0;
+                let gaussian = |x: f32| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(r)).max(0.0)
+            }
+            FilterKind::Mitchell => mitchell(2.0 * x / r),
+            FilterKind::Lanczos => sinc(x) * sinc(x / r),
+        }
+    }
+
+    /// The pixels along an axis of `len` that a sample at `center` reaches,
+    /// where pixel `i` covers `[i, i + 1)`. A box of radius 0.5 reaches
+    /// exactly the pixel the sample is in.
+    pub fn footprint(&self, center: f32, len: usize) -> Range<usize> {
+        // Pixel i is reached when -radius < i + 0.5 - center <= radius.
+        let start = (center - 0.5 - self.radius).floor() + 1.0;
+        let end = (center - 0.5 + self.radius).floor() + 1.0;
+        (start.max(0.0) as usize).min(len)..(end.max(0.0) as usize).min(len)
+    }
+
+    /// How many pixels beyond its own a sample can reach on each side.
+    pub fn reach(&self) -> usize {
+        (self.radius - 0.5).ceil().max(0.0) as usize
+    }
+}
+
+/// Mitchell-Netravali with B = C = 1/3, over [0, 2].
+fn mitchell(x: f32) -> f32 {
+    const B: f32 = 1.0 / 3.0;
+    const C: f32 = 1.0 / 3.0;
+    let weight = if x < 1.0 {
+        (12.0 - 9.0 * B - 6.0 * C) * x * x * x
+            + (-18.0 + 12.0 * B + 6.0 * C) * x * x
+            + (6.0 - 2.0 * B)
+    } else if x < 2.0 {
+        (-B - 6.0 * C) * x * x * x
+            + (6.0 * B + 30.0 * C) * x * x
+            + (-12.0 * B - 48.0 * C) * x
+            + (8.0 * B + 24.0 * C)
+    } else {
+        0.0
+    };
+    weight / 6.0
+}
+
+fn sinc(x: f32) -> f32 {
+    if x < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
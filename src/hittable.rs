@@ -4,24 +4,38 @@ use crate::aabb::Aabb;
 use crate::{material::Material, ray::Ray, Point3, Vec3};
 
 pub mod aa_rect;
+pub mod animated_transform;
 pub mod box_obj;
 pub mod bvh_node;
 pub mod flip_face;
 pub mod hittable_list;
+pub mod linear_bvh;
 pub mod medium;
 pub mod moving_sphere;
 pub mod rotate;
 pub mod sphere;
+pub mod tagged;
+pub mod transform;
 pub mod translate;
+pub mod triangle;
+pub mod triangle_mesh;
 
 pub struct HitRecord {
     pub p: Point3,
+    /// The normal to shade with, facing against the ray.
     pub normal: Vec3,
+    /// The surface's own normal, on the same side as `normal`, which vertex
+    /// normals can tilt away from it.
+    pub geometric_normal: Vec3,
     pub t: f32,
     pub u: f32,
     pub v: f32,
     pub front_face: bool,
     pub mat_ptr: Arc<dyn Material>,
+    /// How fast the surface at `p` is moving at the ray's time.
+    pub velocity: Vec3,
+    /// Set by `Tagged` to tell objects apart; 0 when untagged.
+    pub object_id: u32,
 }
 
 impl HitRecord {
@@ -43,11 +57,14 @@ impl HitRecord {
         Self {
             p,
             normal,
+            geometric_normal: normal,
             t,
             u,
             v,
             front_face,
             mat_ptr,
+            velocity: Vec3::zeros(),
+            object_id: 0,
         }
     }
 }
@@ -61,4 +78,29 @@ pub trait Hittable: Send + Sync {
     fn random(&self, _o: Vec3) -> Vec3 {
         Vec3::new(1.0, 0.0, 0.0)
     }
+    /// Calls `f` with the material of every surface in the object, in the
+    /// order the object was put together.
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>));
+}
+
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        (**self).hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        (**self).bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f32 {
+        (**self).pdf_value(o, v)
+    }
+
+    fn random(&self, o: Vec3) -> Vec3 {
+        (**self).random(o)
+    }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        (**self).materials(f)
+    }
 }
@@ -1,10 +1,9 @@
 use std::sync::Arc;
 
-use rand::Rng;
-
 use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::Ray;
+use crate::sampler;
 use crate::vec3::Vec3;
 use crate::Point3;
 
@@ -81,14 +80,18 @@ impl Hittable for XyRect {
     }
 
     fn random(&self, o: Vec3) -> Vec3 {
-        let mut rng = rand::thread_rng();
+        let [s, t] = sampler::next_2d();
         let random_point = Point3::new(
-            rng.gen_range(self.x0..self.x1),
-            rng.gen_range(self.y0..self.y1),
+            self.x0 + s * (self.x1 - self.x0),
+            self.y0 + t * (self.y1 - self.y0),
             self.k,
         );
         random_point - o
     }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.mp);
+    }
 }
 
 pub struct XzRect {
@@ -161,14 +164,18 @@ impl Hittable for XzRect {
     }
 
     fn random(&self, o: Vec3) -> Vec3 {
-        let mut rng = rand::thread_rng();
+        let [s, t] = sampler::next_2d();
         let random_point = Point3::new(
-            rng.gen_range(self.x0..self.x1),
+            self.x0 + s * (self.x1 - self.x0),
             self.k,
-            rng.gen_range(self.z0..self.z1),
+            self.z0 + t * (self.z1 - self.z0),
         );
         random_point - o
     }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.mp);
+    }
 }
 
 pub struct YzRect {
@@ -241,12 +248,16 @@ impl Hittable for YzRect {
     }
 
     fn random(&self, o: Vec3) -> Vec3 {
-        let mut rng = rand::thread_rng();
+        let [s, t] = sampler::next_2d();
         let random_point = Point3::new(
             self.k,
-            rng.gen_range(self.y0..self.y1),
-            rng.gen_range(self.z0..self.z1),
+            self.y0 + s * (self.y1 - self.y0),
+            self.z0 + t * (self.z1 - self.z0),
         );
         random_point - o
     }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.mp);
+    }
 }
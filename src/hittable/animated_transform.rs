@@ -0,0 +1,174 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use nalgebra::UnitQuaternion;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use crate::Point3;
+
+use super::HitRecord;
+use super::Hittable;
+
+/// Bounding box samples per keyframe interval.
+const BOX_STEPS: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translation: Vec3,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vec3,
+}
+
+/// Moves an object through a list of keyframes, using each ray's time to
+/// pick where it is. Translation and scale are interpolated linearly and
+/// rotation by slerp; before the first and after the last keyframe the
+/// object holds still.
+///
+/// Light sampling has no notion of time, so an animated object can be hit by
+/// rays but not sampled as a light.
+pub struct AnimatedTransform {
+    object: Arc<dyn Hittable>,
+    keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    pub fn new(object: Arc<dyn Hittable>, mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(
+            !keyframes.is_empty(),
+            "an animation needs at least one keyframe"
+        );
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        for frame in &keyframes {
+            assert!(
+                frame.scale.iter().all(|s| *s != 0.0),
+                "keyframe at time {} has a zero scale",
+                frame.time
+            );
+        }
+        for pair in keyframes.windows(2) {
+            assert!(
+                pair[0].rotation.angle_to(&pair[1].rotation) < PI - 1e-4,
+                "keyframes at times {} and {} are half a turn apart, which has no \
+                 unique shortest rotation; add a keyframe in between",
+                pair[0].time,
+                pair[1].time
+            );
+        }
+        Self { object, keyframes }
+    }
+
+    fn at(&self, time: f32) -> (Vec3, UnitQuaternion<f32>, Vec3) {
+        let first = &self.keyframes[0];
+        let last = self.keyframes.last().unwrap();
+        if time <= first.time {
+            return (first.translation, first.rotation, first.scale);
+        }
+        if time >= last.time {
+            return (last.translation, last.rotation, last.scale);
+        }
+
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let (k0, k1) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        let s = (time - k0.time) / (k1.time - k0.time);
+        (
+            k0.translation + s * (k1.translation - k0.translation),
+            k0.rotation.slerp(&k1.rotation, s),
+            k0.scale + s * (k1.scale - k0.scale),
+        )
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (translation, rotation, scale) = self.at(ray.time);
+        let to_object = |v: Vec3| -> Vec3 {
+            Vec3::from(rotation.inverse_transform_vector(&v))
+                .component_div(&scale)
+                .into()
+        };
+        let object_ray = Ray::new(
+            to_object(ray.origin - translation),
+            to_object(ray.direction),
+            ray.time,
+        );
+        let mut rec = self.object.hit(&object_ray, t_min, t_max)?;
+
+        // The surface point's velocity is its own, carried along, plus the
+        // transform's, which is easiest to take numerically.
+        let at = |time: f32| {
+            let (translation, rotation, scale) = self.at(time);
+            Vec3::from(rotation * rec.p.component_mul(&scale)) + translation
+        };
+        let dt = 1e-3;
+        let carried = Vec3::from(rotation * rec.velocity.component_mul(&scale));
+        rec.velocity = carried + (at(ray.time + dt) - at(ray.time - dt)) / (2.0 * dt);
+        rec.p = Vec3::from(rotation * rec.p.component_mul(&scale)) + translation;
+        let normal = |n: Vec3| Vec3::from(rotation * n.component_div(&scale)).normalize();
+        rec.normal = normal(rec.normal);
+        rec.geometric_normal = normal(rec.geometric_normal);
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        let bb = self.object.bounding_box(time0, time1)?;
+        let corners = (0..8).map(|i| {
+            Point3::new(
+                if i & 1 == 0 { bb.min.x } else { bb.max.x },
+                if i & 2 == 0 { bb.min.y } else { bb.max.y },
+                if i & 4 == 0 { bb.min.z } else { bb.max.z },
+            )
+        });
+
+        // Sample the shutter interval at every keyframe inside it and at
+        // regular steps between them.
+        let mut times = vec![time0];
+        let mut edges = self
+            .keyframes
+            .iter()
+            .map(|k| k.time)
+            .filter(|t| *t > time0 && *t < time1)
+            .collect::<Vec<_>>();
+        edges.push(time1);
+        for edge in edges {
+            let start = *times.last().unwrap();
+            times.extend(
+                (1..=BOX_STEPS).map(|i| start + (edge - start) * i as f32 / BOX_STEPS as f32),
+            );
+        }
+
+        let mut min = Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        let mut max_radius = 0.0f32;
+        let mut max_step_angle = 0.0f32;
+        let mut previous_rotation = None;
+        for &time in &times {
+            let (translation, rotation, scale) = self.at(time);
+            for corner in corners.clone() {
+                let scaled = Vec3::from(corner.component_mul(&scale));
+                max_radius = max_radius.max(scaled.magnitude());
+                let p = Vec3::from(rotation * *scaled) + translation;
+                for a in 0..3 {
+                    min[a] = min[a].min(p[a]);
+                    max[a] = max[a].max(p[a]);
+                }
+            }
+            if let Some(previous) = previous_rotation.replace(rotation) {
+                max_step_angle = max_step_angle.max(rotation.angle_to(&previous));
+            }
+        }
+
+        // Between samples a corner swings along an arc rather than the chord
+        // the samples see, so pad by the arc's largest distance from it.
+        let pad = max_radius * (1.0 - (max_step_angle / 2.0).cos()) + 0.0001;
+        let pad = Vec3::new(pad, pad, pad);
+        Some(Aabb::new(min - pad, max + pad))
+    }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.object.materials(f);
+    }
+}
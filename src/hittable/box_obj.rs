@@ -85,4 +85,8 @@ impl Hittable for BoxObj {
     fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
         Some(Aabb::new(self.min, self.max))
     }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.sides.materials(f);
+    }
 }
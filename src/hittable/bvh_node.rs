@@ -1,57 +1,332 @@
-use std::cmp::Ordering;
+use std::fmt;
 use std::sync::Arc;
 
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 use crate::aabb::Aabb;
+use crate::material::Material;
 use crate::ray::Ray;
+use crate::Point3;
 
 use super::HitRecord;
 use super::Hittable;
 
+/// Cost of visiting an interior node relative to intersecting one object.
+const TRAVERSAL_COST: f32 = 0.125;
+const INTERSECTION_COST: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SplitMethod {
+    /// Binned surface area heuristic.
+    Sah,
+    /// Split at the median centroid along the widest axis.
+    Median,
+    /// The original builder's split: sort by the boxes' low corners along
+    /// a random axis and cut the list in half. The axes come from a fixed
+    /// seed, so the tree is the same every run.
+    Random,
+}
+
+#[derive(Debug, Clone)]
+pub struct BvhOptions {
+    pub split: SplitMethod,
+    /// Nodes with at most this many objects may become leaves.
+    pub max_leaf_size: usize,
+    /// Number of buckets the SAH evaluates per axis.
+    pub bins: usize,
+}
+
+impl Default for BvhOptions {
+    fn default() -> Self {
+        Self {
+            split: SplitMethod::Sah,
+            max_leaf_size: 4,
+            bins: 16,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct BvhStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub max_depth: usize,
+    pub max_leaf_size: usize,
+    /// Expected cost of a random ray, in units of one object intersection.
+    pub sah_cost: f32,
+}
+
+impl fmt::Display for BvhStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} nodes, {} leaves, depth {}, largest leaf {}, SAH cost {:.2}",
+            self.nodes, self.leaves, self.max_depth, self.max_leaf_size, self.sah_cost
+        )
+    }
+}
+
+pub(super) enum Contents {
+    Leaf(Vec<Arc<dyn Hittable>>),
+    Interior {
+        left: Box<BvhNode>,
+        right: Box<BvhNode>,
+        /// The axis the children were split along.
+        axis: usize,
+    },
+}
+
 pub struct BvhNode {
-    left: Arc<dyn Hittable>,
-    right: Arc<dyn Hittable>,
+    pub(super) contents: Contents,
+    pub(super) bb: Aabb,
+}
+
+/// An object with its bounds cached for the build.
+struct Primitive {
+    object: Arc<dyn Hittable>,
     bb: Aabb,
+    centroid: Point3,
 }
 
 impl BvhNode {
     pub fn new(list: &[Arc<dyn Hittable>], time0: f32, time1: f32) -> Self {
-        let mut objects = list.to_vec();
+        Self::with_options(list, time0, time1, &BvhOptions::default())
+    }
 
-        let axis = rand::thread_rng().gen_range(0..3);
-        let comparator = |a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>| -> Ordering {
-            let box_a = a.bounding_box(0.0, 0.0).unwrap();
-            let box_b = b.bounding_box(0.0, 0.0).unwrap();
-            box_a.min[axis].total_cmp(&box_b.min[axis])
+    /// Objects without a bounding box, such as empty lists, have nothing a
+    /// ray could hit and are left out of the tree.
+    pub fn with_options(
+        list: &[Arc<dyn Hittable>],
+        time0: f32,
+        time1: f32,
+        options: &BvhOptions,
+    ) -> Self {
+        let primitives = list
+            .iter()
+            .filter_map(|object| {
+                let bb = object.bounding_box(time0, time1)?;
+                Some(Primitive {
+                    object: object.clone(),
+                    centroid: bb.centroid(),
+                    bb,
+                })
+            })
+            .collect();
+        Self::build(primitives, options, &mut StdRng::seed_from_u64(0))
+    }
+
+    fn build(primitives: Vec<Primitive>, options: &BvhOptions, rng: &mut StdRng) -> Self {
+        let bb = match primitives
+            .iter()
+            .map(|p| p.bb.clone())
+            .reduce(|a, b| Aabb::surrounding(&a, &b))
+        {
+            Some(bb) => bb,
+            None => {
+                return Self {
+                    contents: Contents::Leaf(Vec::new()),
+                    bb: Aabb::new(Point3::zeros(), Point3::zeros()),
+                }
+            }
         };
 
-        let (left, right) = match objects.len() {
-            1 => (objects[0].clone(), objects[0].clone()),
-            2 => {
-                if comparator(&objects[0], &objects[1]) == Ordering::Less {
-                    (objects[0].clone(), objects[1].clone())
-                } else {
-                    (objects[1].clone(), objects[0].clone())
+        let split = if primitives.len() <= 1 {
+            None
+        } else {
+            match options.split {
+                SplitMethod::Sah => sah_split(&primitives, &bb, options),
+                SplitMethod::Median => {
+                    (primitives.len() > options.max_leaf_size).then(|| median_split(&primitives))
                 }
+                SplitMethod::Random => (primitives.len() > options.max_leaf_size)
+                    .then(|| random_split(&primitives, rng)),
             }
-            _ => {
-                objects.sort_unstable_by(comparator);
-                let mid = objects.len() / 2;
-                let left = Arc::new(BvhNode::new(&objects[..mid], time0, time1));
-                let right = Arc::new(BvhNode::new(&objects[mid + 1..], time0, time1));
-                (left as Arc<dyn Hittable>, right as Arc<dyn Hittable>)
+        };
+
+        match split {
+            Some((axis, goes_left)) => {
+                let (left, right): (Vec<_>, Vec<_>) = primitives
+                    .into_iter()
+                    .zip(goes_left)
+                    .partition(|(_, left)| *left);
+                let left = left.into_iter().map(|(p, _)| p).collect();
+                let right = right.into_iter().map(|(p, _)| p).collect();
+                Self {
+                    contents: Contents::Interior {
+                        left: Box::new(Self::build(left, options, rng)),
+                        right: Box::new(Self::build(right, options, rng)),
+                        axis,
+                    },
+                    bb,
+                }
             }
+            None => Self {
+                contents: Contents::Leaf(primitives.into_iter().map(|p| p.object).collect()),
+                bb,
+            },
+        }
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        let mut stats = BvhStats::default();
+        let root_area = self.bb.surface_area();
+        self.collect_stats(&mut stats, 1, root_area);
+        stats
+    }
+
+    fn collect_stats(&self, stats: &mut BvhStats, depth: usize, root_area: f32) {
+        stats.nodes += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        let area_ratio = if root_area > 0.0 {
+            self.bb.surface_area() / root_area
+        } else {
+            1.0
         };
+        match &self.contents {
+            Contents::Leaf(objects) => {
+                stats.leaves += 1;
+                stats.max_leaf_size = stats.max_leaf_size.max(objects.len());
+                stats.sah_cost += area_ratio * objects.len() as f32 * INTERSECTION_COST;
+            }
+            Contents::Interior { left, right, .. } => {
+                stats.sah_cost += area_ratio * TRAVERSAL_COST;
+                left.collect_stats(stats, depth + 1, root_area);
+                right.collect_stats(stats, depth + 1, root_area);
+            }
+        }
+    }
+}
 
-        let box_left = left.bounding_box(time0, time1).unwrap();
-        let box_right = right.bounding_box(time0, time1).unwrap();
+/// Picks the cheapest bucket boundary on any axis, returning that axis and
+/// which side each primitive goes to, or `None` when a leaf is cheaper and
+/// allowed.
+fn sah_split(
+    primitives: &[Primitive],
+    bb: &Aabb,
+    options: &BvhOptions,
+) -> Option<(usize, Vec<bool>)> {
+    let centroid_bounds = primitives
+        .iter()
+        .map(|p| Aabb::new(p.centroid, p.centroid))
+        .reduce(|a, b| Aabb::surrounding(&a, &b))
+        .unwrap();
+    let bins = options.bins.max(2);
+    let bin_of = |p: &Primitive, axis: usize| {
+        let extent = centroid_bounds.max[axis] - centroid_bounds.min[axis];
+        let offset = (p.centroid[axis] - centroid_bounds.min[axis]) / extent;
+        ((offset * bins as f32) as usize).min(bins - 1)
+    };
 
-        Self {
-            left,
-            right,
-            bb: Aabb::surrounding(&box_left, &box_right),
+    let mut best: Option<(f32, usize, usize)> = None;
+    for axis in 0..3 {
+        if centroid_bounds.max[axis] <= centroid_bounds.min[axis] {
+            continue;
+        }
+
+        let mut counts = vec![0usize; bins];
+        let mut bounds: Vec<Option<Aabb>> = vec![None; bins];
+        for p in primitives {
+            let b = bin_of(p, axis);
+            counts[b] += 1;
+            bounds[b] = Some(match &bounds[b] {
+                Some(existing) => Aabb::surrounding(existing, &p.bb),
+                None => p.bb.clone(),
+            });
+        }
+
+        // Area and count of everything right of each boundary, swept from
+        // the right so each candidate costs O(1).
+        let mut right_area = vec![0.0; bins];
+        let mut right_count = vec![0; bins];
+        let mut acc: Option<Aabb> = None;
+        let mut count = 0;
+        for b in (1..bins).rev() {
+            acc = merge(acc, &bounds[b]);
+            count += counts[b];
+            right_area[b] = acc.as_ref().map_or(0.0, Aabb::surface_area);
+            right_count[b] = count;
+        }
+
+        let mut acc: Option<Aabb> = None;
+        let mut count = 0;
+        for b in 1..bins {
+            acc = merge(acc, &bounds[b - 1]);
+            count += counts[b - 1];
+            if count == 0 || right_count[b] == 0 {
+                continue;
+            }
+            let left_area = acc.as_ref().map_or(0.0, Aabb::surface_area);
+            let cost = TRAVERSAL_COST
+                + INTERSECTION_COST
+                    * (left_area * count as f32 + right_area[b] * right_count[b] as f32)
+                    / bb.surface_area();
+            if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                best = Some((cost, axis, b));
+            }
+        }
+    }
+
+    let leaf_cost = primitives.len() as f32 * INTERSECTION_COST;
+    match best {
+        Some((cost, axis, boundary)) => {
+            if primitives.len() <= options.max_leaf_size && leaf_cost <= cost {
+                None
+            } else {
+                Some((
+                    axis,
+                    primitives
+                        .iter()
+                        .map(|p| bin_of(p, axis) < boundary)
+                        .collect(),
+                ))
+            }
         }
+        // Every centroid coincides, so no boundary separates them: halve
+        // the list if it is too big for a leaf.
+        None => (primitives.len() > options.max_leaf_size).then(|| {
+            (
+                0,
+                (0..primitives.len())
+                    .map(|i| i < primitives.len() / 2)
+                    .collect(),
+            )
+        }),
+    }
+}
+
+fn median_split(primitives: &[Primitive]) -> (usize, Vec<bool>) {
+    let centroid_bounds = primitives
+        .iter()
+        .map(|p| Aabb::new(p.centroid, p.centroid))
+        .reduce(|a, b| Aabb::surrounding(&a, &b))
+        .unwrap();
+    let extent = centroid_bounds.max - centroid_bounds.min;
+    let axis = extent.imax();
+    (axis, halve(primitives, |p| p.centroid[axis]))
+}
+
+fn random_split(primitives: &[Primitive], rng: &mut StdRng) -> (usize, Vec<bool>) {
+    let axis = rng.gen_range(0..3);
+    (axis, halve(primitives, |p| p.bb.min[axis]))
+}
+
+/// Sends the half of `primitives` with the smaller `key` left.
+fn halve(primitives: &[Primitive], key: impl Fn(&Primitive) -> f32) -> Vec<bool> {
+    let mut order = (0..primitives.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| key(&primitives[a]).total_cmp(&key(&primitives[b])));
+    let mut goes_left = vec![false; primitives.len()];
+    for &i in &order[..primitives.len() / 2] {
+        goes_left[i] = true;
+    }
+    goes_left
+}
+
+fn merge(acc: Option<Aabb>, bb: &Option<Aabb>) -> Option<Aabb> {
+    match (acc, bb) {
+        (Some(a), Some(b)) => Some(Aabb::surrounding(&a, b)),
+        (Some(a), None) => Some(a),
+        (None, b) => b.clone(),
     }
 }
 
@@ -61,16 +336,48 @@ impl Hittable for BvhNode {
             return None;
         }
 
-        let hit_left = self.left.hit(ray, t_min, t_max);
-        let hit_right = self.right.hit(
-            ray,
-            t_min,
-            hit_left.as_ref().map(|rec| rec.t).unwrap_or(t_max),
-        );
-        hit_right.or(hit_left)
+        match &self.contents {
+            Contents::Leaf(objects) => {
+                let mut closest = None;
+                let mut closest_so_far = t_max;
+                for object in objects {
+                    if let Some(rec) = object.hit(ray, t_min, closest_so_far) {
+                        closest_so_far = rec.t;
+                        closest = Some(rec);
+                    }
+                }
+                closest
+            }
+            Contents::Interior { left, right, .. } => {
+                let hit_left = left.hit(ray, t_min, t_max);
+                let hit_right = right.hit(
+                    ray,
+                    t_min,
+                    hit_left.as_ref().map(|rec| rec.t).unwrap_or(t_max),
+                );
+                hit_right.or(hit_left)
+            }
+        }
     }
 
     fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
-        Some(self.bb.clone())
+        match &self.contents {
+            Contents::Leaf(objects) if objects.is_empty() => None,
+            _ => Some(self.bb.clone()),
+        }
+    }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        match &self.contents {
+            Contents::Leaf(objects) => {
+                for object in objects {
+                    object.materials(f);
+                }
+            }
+            Contents::Interior { left, right, .. } => {
+                left.materials(f);
+                right.materials(f);
+            }
+        }
     }
 }
@@ -1,4 +1,6 @@
-use crate::{aabb::Aabb, ray::Ray};
+use std::sync::Arc;
+
+use crate::{aabb::Aabb, material::Material, ray::Ray};
 
 use super::{HitRecord, Hittable};
 
@@ -19,15 +21,22 @@ impl Hittable for FlipFace {
         Some(HitRecord {
             p: rec.p,
             normal: rec.normal,
+            geometric_normal: rec.geometric_normal,
             t: rec.t,
             u: rec.u,
             v: rec.v,
             front_face: !rec.front_face,
             mat_ptr: rec.mat_ptr,
+            velocity: rec.velocity,
+            object_id: rec.object_id,
         })
     }
 
     fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
         self.ptr.bounding_box(time0, time1)
     }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.ptr.materials(f);
+    }
 }
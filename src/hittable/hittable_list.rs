@@ -1,11 +1,11 @@
 use std::sync::Arc;
 
+use crate::material::Material;
+use crate::sampler;
 use crate::{aabb::Aabb, vec3::Vec3, Point3};
 
 use super::{HitRecord, Hittable};
 
-use rand::seq::SliceRandom;
-
 pub struct HittableList {
     objects: Vec<Arc<dyn Hittable>>,
 }
@@ -54,6 +54,9 @@ impl Hittable for HittableList {
     }
 
     fn pdf_value(&self, o: Point3, v: Vec3) -> f32 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
         self.objects
             .iter()
             .map(|obj| obj.pdf_value(o, v))
@@ -62,9 +65,20 @@ impl Hittable for HittableList {
     }
 
     fn random(&self, o: Vec3) -> Vec3 {
-        self.objects
-            .choose(&mut rand::thread_rng())
-            .map(|obj| obj.random(o))
-            .unwrap_or(Vec3::zeros())
+        if self.objects.is_empty() {
+            return Vec3::zeros();
+        }
+        let len = self.objects.len();
+        if len == 1 {
+            return self.objects[0].random(o);
+        }
+        let i = ((sampler::next_1d() * len as f32) as usize).min(len - 1);
+        self.objects[i].random(o)
+    }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        for object in &self.objects {
+            object.materials(f);
+        }
     }
 }
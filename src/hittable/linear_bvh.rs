@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::ray::Ray;
+
+use super::bvh_node::{BvhNode, Contents};
+use super::HitRecord;
+use super::Hittable;
+
+/// Deepest tree whose traversal stack fits on the call stack; deeper ones,
+/// which only pathological scenes build, get one on the heap.
+const MAX_DEPTH: usize = 64;
+
+struct LinearNode {
+    bb: Aabb,
+    /// First primitive of a leaf, or the index of an interior node's second
+    /// child. The first child always directly follows its parent.
+    offset: u32,
+    /// Number of primitives; zero for interior nodes.
+    count: u32,
+    axis: u8,
+}
+
+/// A `BvhNode` flattened depth-first into one array, traversed with an
+/// explicit stack instead of recursion.
+pub struct LinearBvh {
+    nodes: Vec<LinearNode>,
+    primitives: Vec<Arc<dyn Hittable>>,
+    /// Levels in the tree, counting the root's.
+    depth: usize,
+}
+
+impl From<BvhNode> for LinearBvh {
+    fn from(root: BvhNode) -> Self {
+        let mut bvh = LinearBvh {
+            nodes: Vec::new(),
+            primitives: Vec::new(),
+            depth: 0,
+        };
+        if root.bounding_box(0.0, 0.0).is_some() {
+            bvh.flatten(root, 1);
+        }
+        bvh
+    }
+}
+
+impl LinearBvh {
+    /// Builds a tree over `list` with the default options.
+    pub fn new(list: &[Arc<dyn Hittable>], time0: f32, time1: f32) -> Self {
+        BvhNode::new(list, time0, time1).into()
+    }
+
+    fn flatten(&mut self, node: BvhNode, depth: usize) -> usize {
+        self.depth = self.depth.max(depth);
+        let index = self.nodes.len();
+        self.nodes.push(LinearNode {
+            bb: node.bb,
+            offset: 0,
+            count: 0,
+            axis: 0,
+        });
+        match node.contents {
+            Contents::Leaf(objects) => {
+                self.nodes[index].offset = self.primitives.len() as u32;
+                self.nodes[index].count = objects.len() as u32;
+                self.primitives.extend(objects);
+            }
+            Contents::Interior { left, right, axis } => {
+                self.flatten(*left, depth + 1);
+                let second = self.flatten(*right, depth + 1);
+                self.nodes[index].offset = second as u32;
+                self.nodes[index].axis = axis as u8;
+            }
+        }
+        index
+    }
+
+    /// Finds the closest hit, keeping the far children it has yet to visit
+    /// on `stack`, which must have room for one per level of the tree.
+    fn traverse(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stack: &mut [usize],
+    ) -> Option<HitRecord> {
+        let mut closest = None;
+        let mut closest_so_far = t_max;
+        let mut stack_len = 0;
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            if node.bb.hit(ray, t_min, closest_so_far) {
+                if node.count > 0 {
+                    let first = node.offset as usize;
+                    for object in &self.primitives[first..first + node.count as usize] {
+                        if let Some(rec) = object.hit(ray, t_min, closest_so_far) {
+                            closest_so_far = rec.t;
+                            closest = Some(rec);
+                        }
+                    }
+                } else {
+                    // Visit the child nearer the ray origin first so later
+                    // boxes can be culled against the closer hit.
+                    let (near, far) = if ray.inv_direction[node.axis as usize] < 0.0 {
+                        (node.offset as usize, current + 1)
+                    } else {
+                        (current + 1, node.offset as usize)
+                    };
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    current = near;
+                    continue;
+                }
+            }
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            current = stack[stack_len];
+        }
+        closest
+    }
+}
+
+impl Hittable for LinearBvh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        if self.depth <= MAX_DEPTH {
+            self.traverse(ray, t_min, t_max, &mut [0; MAX_DEPTH])
+        } else {
+            self.traverse(ray, t_min, t_max, &mut vec![0; self.depth])
+        }
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bb.clone())
+    }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        for primitive in &self.primitives {
+            primitive.materials(f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::lambertian::Lambertian;
+    use crate::vec3::Vec3;
+    use crate::{Color, Point3};
+
+    #[test]
+    fn trees_deeper_than_the_fixed_stack_are_traversed() {
+        // A chain that splits one sphere off at each level, as the builder
+        // can when objects are spaced out geometrically.
+        let material = Arc::new(Lambertian::new_color(Color::new(0.5, 0.5, 0.5)));
+        let centers = (0..3 * MAX_DEPTH)
+            .map(|i| 3.0 * i as f32)
+            .collect::<Vec<_>>();
+        let leaf = |x: f32| {
+            let sphere: Arc<dyn Hittable> =
+                Arc::new(Sphere::new(Point3::new(x, 0.0, 0.0), 1.0, material.clone()));
+            BvhNode {
+                bb: sphere.bounding_box(0.0, 1.0).unwrap(),
+                contents: Contents::Leaf(vec![sphere]),
+            }
+        };
+        let (&last, rest) = centers.split_last().unwrap();
+        // Each level puts off its leaf until the rest of the chain is done,
+        // so every one of them waits on the stack at once.
+        let root = rest.iter().rev().fold(leaf(last), |left, &x| {
+            let right = leaf(x);
+            BvhNode {
+                bb: Aabb::surrounding(&left.bb, &right.bb),
+                contents: Contents::Interior {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    axis: 0,
+                },
+            }
+        });
+        assert!(root.stats().max_depth > MAX_DEPTH);
+        let bvh = LinearBvh::from(root);
+        for &x in &centers {
+            let ray = Ray::new(Point3::new(x, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+            let rec = bvh
+                .hit(&ray, 0.001, f32::INFINITY)
+                .expect("missed a sphere");
+            assert!(
+                (rec.p - Point3::new(x, 0.0, -1.0)).norm() < 1e-4,
+                "{:?}",
+                rec.p
+            );
+        }
+    }
+}
@@ -9,6 +9,7 @@ use crate::hittable::Hittable;
 use crate::material::isotropic::Isotropic;
 use crate::material::Material;
 use crate::ray::Ray;
+use crate::rng;
 use crate::texture::Texture;
 use crate::vec3::Vec3;
 use crate::Color;
@@ -40,7 +41,7 @@ impl ConstantMedium {
 impl Hittable for ConstantMedium {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
         const enable_debug: bool = false;
-        let debugging = enable_debug && rand::thread_rng().gen_ratio(1, 100000);
+        let debugging = enable_debug && rng::rng().gen_ratio(1, 100000);
 
         let mut rec1 = self.boundary.hit(ray, f32::NEG_INFINITY, f32::INFINITY)?;
         let mut rec2 = self.boundary.hit(ray, rec1.t + 0.0001, f32::INFINITY)?;
@@ -60,10 +61,8 @@ impl Hittable for ConstantMedium {
 
         let ray_length = ray.direction.magnitude();
         let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
-        let hit_distance = self.neg_inv_density
-            * rand::thread_rng()
-                .gen_range(0.0f32..1.0)
-                .log(f32::consts::E);
+        let hit_distance =
+            self.neg_inv_density * rng::rng().gen_range(0.0f32..1.0).log(f32::consts::E);
 
         if hit_distance > distance_inside_boundary {
             return None;
@@ -87,15 +86,24 @@ impl Hittable for ConstantMedium {
         Some(HitRecord {
             p,
             normal,
+            geometric_normal: normal,
             t,
             u: 0.0,
             v: 0.0,
             front_face,
             mat_ptr,
+            velocity: rec1.velocity,
+            object_id: rec1.object_id,
         })
     }
 
     fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
         self.boundary.bounding_box(time0, time1)
     }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        // Hits inside the medium scatter off the phase function, never the
+        // boundary's own material.
+        f(&self.phase_function);
+    }
 }
@@ -70,15 +70,10 @@ impl Hittable for MovingSphere {
         let p = ray.at(t);
         let normal = (p - self.center(ray.time)) / self.radius;
         let (u, v) = Sphere::get_uv(normal);
-        Some(HitRecord::new(
-            p,
-            normal,
-            t,
-            u,
-            v,
-            ray,
-            self.mat_ptr.clone(),
-        ))
+        Some(HitRecord {
+            velocity: (self.center1 - self.center0) / (self.time1 - self.time0),
+            ..HitRecord::new(p, normal, t, u, v, ray, self.mat_ptr.clone())
+        })
     }
 
     fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
@@ -92,4 +87,8 @@ impl Hittable for MovingSphere {
         );
         Some(Aabb::surrounding(&box0, &box1))
     }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.mat_ptr);
+    }
 }
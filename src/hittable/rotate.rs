@@ -1,4 +1,7 @@
+use std::sync::Arc;
+
 use crate::aabb::Aabb;
+use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
 use crate::Point3;
@@ -69,27 +72,28 @@ impl Hittable for RotateY {
 
         let rec = self.ptr.hit(&rotated_r, t_min, t_max)?;
 
-        let mut p = rec.p;
-        let mut normal = rec.normal;
-
-        p[0] = self.cos_theta * rec.p[0] + self.sin_theta * rec.p[2];
-        p[2] = -self.sin_theta * rec.p[0] + self.cos_theta * rec.p[2];
-
-        normal[0] = self.cos_theta * rec.normal[0] + self.sin_theta * rec.normal[2];
-        normal[2] = -self.sin_theta * rec.normal[0] + self.cos_theta * rec.normal[2];
-
-        Some(HitRecord::new(
-            p,
-            normal,
-            rec.t,
-            rec.u,
-            rec.v,
-            &rotated_r,
-            rec.mat_ptr,
-        ))
+        let rotate = |v: Vec3| {
+            let mut rotated = v;
+            rotated[0] = self.cos_theta * v[0] + self.sin_theta * v[2];
+            rotated[2] = -self.sin_theta * v[0] + self.cos_theta * v[2];
+            rotated
+        };
+        let p = rotate(rec.p);
+        let normal = rotate(rec.normal);
+
+        Some(HitRecord {
+            geometric_normal: rotate(rec.geometric_normal),
+            velocity: rotate(rec.velocity),
+            object_id: rec.object_id,
+            ..HitRecord::new(p, normal, rec.t, rec.u, rec.v, &rotated_r, rec.mat_ptr)
+        })
     }
 
     fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
         self.bbox.clone()
     }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.ptr.materials(f);
+    }
 }
@@ -1,10 +1,9 @@
 use std::f32;
 use std::sync::Arc;
 
-use rand::Rng;
-
 use crate::aabb::Aabb;
 use crate::onb::Onb;
+use crate::sampler;
 use crate::vec3::Vec3;
 use crate::{material::Material, ray::Ray, Point3};
 
@@ -34,9 +33,7 @@ impl Sphere {
 }
 
 fn random_to_sphere(radius: f32, distance_squared: f32) -> Vec3 {
-    let mut rng = rand::thread_rng();
-    let r1 = rng.gen_range(0.0..1.0);
-    let r2 = rng.gen_range(0.0..1.0);
+    let [r1, r2] = sampler::next_2d();
     let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).sqrt() - 1.0);
 
     let phi = f32::consts::TAU * r1;
@@ -111,4 +108,8 @@ impl Hittable for Sphere {
         let uvw = Onb::from_w(&direction);
         uvw.local(random_to_sphere(self.radius, distance_squared))
     }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.mat_ptr);
+    }
 }
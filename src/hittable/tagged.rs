@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use crate::Point3;
+
+use super::HitRecord;
+use super::Hittable;
+
+/// Stamps `id` on every hit of the object it wraps, so an object ID buffer
+/// can tell the objects of a scene apart.
+pub struct Tagged {
+    object: Arc<dyn Hittable>,
+    id: u32,
+}
+
+impl Tagged {
+    pub fn new(object: Arc<dyn Hittable>, id: u32) -> Self {
+        Self { object, id }
+    }
+}
+
+impl Hittable for Tagged {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut rec = self.object.hit(ray, t_min, t_max)?;
+        rec.object_id = self.id;
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.object.bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f32 {
+        self.object.pdf_value(o, v)
+    }
+
+    fn random(&self, o: Vec3) -> Vec3 {
+        self.object.random(o)
+    }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.object.materials(f);
+    }
+}
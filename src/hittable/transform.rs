@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use nalgebra::{Matrix3, Matrix4};
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use crate::Point3;
+
+use super::HitRecord;
+use super::Hittable;
+
+/// Places an object with an arbitrary affine matrix: any mix of rotation,
+/// non-uniform scale, shear and translation.
+///
+/// The object is held by `Arc`, so the same geometry (usually a bottom-level
+/// BVH or mesh) can be instanced any number of times for the cost of one
+/// matrix per copy, and a top-level BVH over the instances only has to look
+/// at their boxes.
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    object_to_world: Matrix4<f32>,
+    world_to_object: Matrix4<f32>,
+    /// Inverse transpose of the linear part, for normals.
+    normal_matrix: Matrix3<f32>,
+    /// Determinant of the inverse's linear part, for light sampling.
+    inverse_det: f32,
+}
+
+impl Transform {
+    /// `object_to_world` must be an invertible affine matrix.
+    pub fn new(object: Arc<dyn Hittable>, object_to_world: Matrix4<f32>) -> Self {
+        let world_to_object = object_to_world
+            .try_inverse()
+            .expect("transform matrix is not invertible");
+        Self {
+            object,
+            object_to_world,
+            world_to_object,
+            normal_matrix: world_to_object.fixed_view::<3, 3>(0, 0).transpose(),
+            inverse_det: world_to_object.fixed_view::<3, 3>(0, 0).determinant(),
+        }
+    }
+
+    pub fn translation(object: Arc<dyn Hittable>, offset: Vec3) -> Self {
+        Self::new(object, Matrix4::new_translation(&offset))
+    }
+
+    /// Rotates by `angle` degrees about `axis` through the origin.
+    pub fn rotation(object: Arc<dyn Hittable>, axis: Vec3, angle: f32) -> Self {
+        let axis = nalgebra::Unit::new_normalize(*axis);
+        Self::new(object, Matrix4::from_axis_angle(&axis, angle.to_radians()))
+    }
+
+    pub fn scaling(object: Arc<dyn Hittable>, scale: Vec3) -> Self {
+        Self::new(object, Matrix4::new_nonuniform_scaling(&scale))
+    }
+}
+
+fn transform_point(m: &Matrix4<f32>, p: Point3) -> Point3 {
+    m.transform_point(&nalgebra::Point3::from(*p)).coords.into()
+}
+
+fn transform_vector(m: &Matrix4<f32>, v: Vec3) -> Vec3 {
+    m.transform_vector(&v).into()
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        // The direction is not renormalized, so `t` means the same thing in
+        // both spaces.
+        let object_ray = Ray::new(
+            transform_point(&self.world_to_object, ray.origin),
+            transform_vector(&self.world_to_object, ray.direction),
+            ray.time,
+        );
+        let mut rec = self.object.hit(&object_ray, t_min, t_max)?;
+
+        rec.p = transform_point(&self.object_to_world, rec.p);
+        // The inverse transpose keeps the sign of the normal against the
+        // ray, so `front_face` stays valid.
+        rec.normal = (self.normal_matrix * *rec.normal).normalize().into();
+        rec.geometric_normal = (self.normal_matrix * *rec.geometric_normal)
+            .normalize()
+            .into();
+        rec.velocity = transform_vector(&self.object_to_world, rec.velocity);
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        let bb = self.object.bounding_box(time0, time1)?;
+
+        let mut min = Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for i in 0..8 {
+            let corner = Point3::new(
+                if i & 1 == 0 { bb.min.x } else { bb.max.x },
+                if i & 2 == 0 { bb.min.y } else { bb.max.y },
+                if i & 4 == 0 { bb.min.z } else { bb.max.z },
+            );
+            let corner = transform_point(&self.object_to_world, corner);
+            for a in 0..3 {
+                min[a] = min[a].min(corner[a]);
+                max[a] = max[a].max(corner[a]);
+            }
+        }
+        Some(Aabb::new(min, max))
+    }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f32 {
+        let v = v.normalize();
+        let object_v = transform_vector(&self.world_to_object, v);
+        let object_pdf = self
+            .object
+            .pdf_value(transform_point(&self.world_to_object, o), object_v);
+        // Directions are sampled in object space and mapped out through the
+        // matrix, so convert the density with the Jacobian of
+        // v -> normalize(M⁻¹v), which is |det M⁻¹| / |M⁻¹v|³ for unit v.
+        object_pdf * self.inverse_det.abs() / object_v.magnitude().powi(3)
+    }
+
+    fn random(&self, o: Vec3) -> Vec3 {
+        let object_o = transform_point(&self.world_to_object, o);
+        transform_vector(&self.object_to_world, self.object.random(object_o))
+    }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.object.materials(f);
+    }
+}
@@ -1,4 +1,7 @@
+use std::sync::Arc;
+
 use crate::aabb::Aabb;
+use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
 
@@ -21,15 +24,20 @@ impl Hittable for Translate {
         let moved_r = Ray::new(ray.origin - self.offset, ray.direction, ray.time);
         let rec = self.ptr.hit(&moved_r, t_min, t_max)?;
 
-        Some(HitRecord::new(
-            rec.p + self.offset,
-            rec.normal,
-            rec.t,
-            rec.u,
-            rec.v,
-            &moved_r,
-            rec.mat_ptr,
-        ))
+        Some(HitRecord {
+            geometric_normal: rec.geometric_normal,
+            velocity: rec.velocity,
+            object_id: rec.object_id,
+            ..HitRecord::new(
+                rec.p + self.offset,
+                rec.normal,
+                rec.t,
+                rec.u,
+                rec.v,
+                &moved_r,
+                rec.mat_ptr,
+            )
+        })
     }
 
     fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
@@ -40,4 +48,8 @@ impl Hittable for Translate {
             out_box.max + self.offset,
         ))
     }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.ptr.materials(f);
+    }
 }
@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler;
+use crate::vec3::Vec3;
+use crate::Point3;
+
+use super::HitRecord;
+use super::Hittable;
+
+pub struct Triangle {
+    vertices: [Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f32, f32); 3]>,
+    mat_ptr: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, mat_ptr: Arc<dyn Material>) -> Self {
+        Self {
+            vertices: [v0, v1, v2],
+            normals: None,
+            uvs: None,
+            mat_ptr,
+        }
+    }
+
+    /// Interpolate these per-vertex normals for shading instead of using the
+    /// flat face normal.
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Interpolate these per-vertex texture coordinates instead of reporting
+    /// the barycentric coordinates as `u`/`v`.
+    pub fn with_uvs(mut self, uvs: [(f32, f32); 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+}
+
+/// Möller–Trumbore intersection, returning `t` and the barycentric
+/// coordinates of the second and third vertex.
+fn intersect(vertices: &[Point3; 3], ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32, f32)> {
+    let edge1 = vertices[1] - vertices[0];
+    let edge2 = vertices[2] - vertices[0];
+    let pvec: Vec3 = ray.direction.cross(&edge2).into();
+    let det = edge1.dot(&pvec);
+    if det.abs() < 1e-8 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = ray.origin - vertices[0];
+    let b1 = tvec.dot(&pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec: Vec3 = tvec.cross(&edge1).into();
+    let b2 = ray.direction.dot(&qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(&qvec) * inv_det;
+    if !(t_min..t_max).contains(&t) {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+/// Shared by `Triangle` and the faces of a `TriangleMesh`.
+pub(super) fn hit_triangle(
+    vertices: &[Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f32, f32); 3]>,
+    mat_ptr: &Arc<dyn Material>,
+    ray: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Option<HitRecord> {
+    let (t, b1, b2) = intersect(vertices, ray, t_min, t_max)?;
+    let b0 = 1.0 - b1 - b2;
+
+    let mut outward_normal: Vec3 = (vertices[1] - vertices[0])
+        .cross(&(vertices[2] - vertices[0]))
+        .normalize()
+        .into();
+    let shading_normal = normals.map(|n| (b0 * n[0] + b1 * n[1] + b2 * n[2]).normalize());
+    // Vertex normals decide which side is outside, whatever the winding.
+    if let Some(n) = shading_normal {
+        if n.dot(&outward_normal) < 0.0 {
+            outward_normal = -outward_normal;
+        }
+    }
+
+    let (u, v) = match uvs {
+        Some(uv) => (
+            b0 * uv[0].0 + b1 * uv[1].0 + b2 * uv[2].0,
+            b0 * uv[0].1 + b1 * uv[1].1 + b2 * uv[2].1,
+        ),
+        None => (b1, b2),
+    };
+
+    let mut rec = HitRecord::new(ray.at(t), outward_normal, t, u, v, ray, mat_ptr.clone());
+    if let Some(n) = shading_normal {
+        rec.normal = if rec.front_face { n } else { -n };
+    }
+    Some(rec)
+}
+
+pub(super) fn triangle_bounding_box(vertices: &[Point3; 3]) -> Aabb {
+    let mut min = vertices[0];
+    let mut max = vertices[0];
+    for v in &vertices[1..] {
+        for a in 0..3 {
+            min[a] = min[a].min(v[a]);
+            max[a] = max[a].max(v[a]);
+        }
+    }
+    // Pad so axis-aligned triangles don't produce a flat box.
+    let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+    Aabb::new(min - pad, max + pad)
+}
+
+pub(super) fn triangle_area(vertices: &[Point3; 3]) -> f32 {
+    0.5 * (vertices[1] - vertices[0])
+        .cross(&(vertices[2] - vertices[0]))
+        .magnitude()
+}
+
+pub(super) fn random_point_on_triangle(vertices: &[Point3; 3]) -> Point3 {
+    let [r1, r2] = sampler::next_2d();
+    let r1 = r1.sqrt();
+    (1.0 - r1) * vertices[0] + (r1 * (1.0 - r2)) * vertices[1] + (r1 * r2) * vertices[2]
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_triangle(
+            &self.vertices,
+            self.normals,
+            self.uvs,
+            &self.mat_ptr,
+            ray,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+        Some(triangle_bounding_box(&self.vertices))
+    }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f32 {
+        let rec = match intersect(&self.vertices, &Ray::new(o, v, 0.0), 0.001, f32::INFINITY) {
+            Some(rec) => rec,
+            None => return 0.0,
+        };
+
+        let t = rec.0;
+        let normal = (self.vertices[1] - self.vertices[0])
+            .cross(&(self.vertices[2] - self.vertices[0]))
+            .normalize();
+        let distance_squared = t * t * v.magnitude_squared();
+        let cosine = (v.dot(&normal) / v.magnitude()).abs();
+
+        distance_squared / (cosine * triangle_area(&self.vertices))
+    }
+
+    fn random(&self, o: Vec3) -> Vec3 {
+        random_point_on_triangle(&self.vertices) - o
+    }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.mat_ptr);
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler;
+use crate::vec3::Vec3;
+use crate::Point3;
+
+use super::linear_bvh::LinearBvh;
+use super::triangle::{
+    hit_triangle, random_point_on_triangle, triangle_area, triangle_bounding_box,
+};
+use super::HitRecord;
+use super::Hittable;
+
+/// Vertex data shared by every face of a mesh.
+struct MeshData {
+    positions: Vec<Point3>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f32, f32)>>,
+    indices: Vec<[usize; 3]>,
+}
+
+impl MeshData {
+    fn vertices(&self, face: usize) -> [Point3; 3] {
+        self.indices[face].map(|i| self.positions[i])
+    }
+
+    fn normals(&self, face: usize) -> Option<[Vec3; 3]> {
+        let normals = self.normals.as_ref()?;
+        Some(self.indices[face].map(|i| normals[i]))
+    }
+
+    fn uvs(&self, face: usize) -> Option<[(f32, f32); 3]> {
+        let uvs = self.uvs.as_ref()?;
+        Some(self.indices[face].map(|i| uvs[i]))
+    }
+}
+
+struct MeshTriangle {
+    mesh: Arc<MeshData>,
+    face: usize,
+    mat_ptr: Arc<dyn Material>,
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        hit_triangle(
+            &self.mesh.vertices(self.face),
+            self.mesh.normals(self.face),
+            self.mesh.uvs(self.face),
+            &self.mat_ptr,
+            ray,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn bounding_box(&self, _time0: f32, _time1: f32) -> Option<Aabb> {
+        Some(triangle_bounding_box(&self.mesh.vertices(self.face)))
+    }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        f(&self.mat_ptr);
+    }
+}
+
+pub struct TriangleMesh {
+    mesh: Arc<MeshData>,
+    tree: LinearBvh,
+    /// Running sum of face areas, used to pick a face when sampling the mesh
+    /// as a light.
+    area_cdf: Vec<f32>,
+}
+
+impl TriangleMesh {
+    /// `normals` and `uvs`, when present, are indexed like `positions`.
+    pub fn new(
+        positions: Vec<Point3>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<(f32, f32)>>,
+        indices: Vec<[usize; 3]>,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            !indices.is_empty(),
+            "a triangle mesh needs at least one face"
+        );
+        let mesh = Arc::new(MeshData {
+            positions,
+            normals,
+            uvs,
+            indices,
+        });
+
+        let faces = (0..mesh.indices.len())
+            .map(|face| {
+                Arc::new(MeshTriangle {
+                    mesh: mesh.clone(),
+                    face,
+                    mat_ptr: mat_ptr.clone(),
+                }) as Arc<dyn Hittable>
+            })
+            .collect::<Vec<_>>();
+        let tree = LinearBvh::new(&faces, 0.0, 1.0);
+
+        let area_cdf = (0..mesh.indices.len())
+            .scan(0.0, |total, face| {
+                *total += triangle_area(&mesh.vertices(face));
+                Some(*total)
+            })
+            .collect();
+
+        Self {
+            mesh,
+            tree,
+            area_cdf,
+        }
+    }
+
+    fn total_area(&self) -> f32 {
+        *self.area_cdf.last().unwrap()
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.tree.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, time0: f32, time1: f32) -> Option<Aabb> {
+        self.tree.bounding_box(time0, time1)
+    }
+
+    /// Sums over every face `v` crosses, not just the nearest: `random`
+    /// picks points anywhere on the surface, so on a mesh that isn't convex
+    /// the faces behind the first could have produced `v` too.
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f32 {
+        let ray = Ray::new(o, v, 0.0);
+        let mut pdf = 0.0;
+        let mut t_min = 0.001;
+        while let Some(rec) = self.hit(&ray, t_min, f32::INFINITY) {
+            let distance_squared = rec.t * rec.t * v.magnitude_squared();
+            // Faces are sampled by area, so it's the face itself, not the
+            // smoothed normal shading uses, that foreshortens it.
+            let cosine = (v.dot(&rec.geometric_normal) / v.magnitude()).abs();
+            pdf += distance_squared / (cosine * self.total_area());
+            t_min = rec.t + 0.001;
+        }
+        pdf
+    }
+
+    fn random(&self, o: Vec3) -> Vec3 {
+        let target = sampler::next_1d() * self.total_area();
+        let face = self
+            .area_cdf
+            .partition_point(|&area| area < target)
+            .min(self.area_cdf.len() - 1);
+        random_point_on_triangle(&self.mesh.vertices(face)) - o
+    }
+
+    fn materials(&self, f: &mut dyn FnMut(&Arc<dyn Material>)) {
+        self.tree.materials(f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::triangle::Triangle;
+    use crate::material::lambertian::Lambertian;
+    use crate::Color;
+
+    #[test]
+    fn pdf_ignores_vertex_normals() {
+        let material = Arc::new(Lambertian::new_color(Color::new(0.5, 0.5, 0.5)));
+        let vertices = [
+            Point3::new(-1.0, 0.0, -1.0),
+            Point3::new(1.0, 0.0, -1.0),
+            Point3::new(0.0, 0.0, 1.0),
+        ];
+        let tilted = vec![
+            Vec3::new(0.6, 0.8, 0.0),
+            Vec3::new(-0.6, 0.8, 0.0),
+            Vec3::new(0.0, 0.8, 0.6),
+        ];
+        let mesh = TriangleMesh::new(
+            vertices.to_vec(),
+            Some(tilted),
+            None,
+            vec![[0, 1, 2]],
+            material.clone(),
+        );
+        let triangle = Triangle::new(vertices[0], vertices[1], vertices[2], material);
+        let o = Point3::new(0.3, 2.0, 0.4);
+        for target in [Point3::new(0.0, 0.0, 0.0), Point3::new(0.5, 0.0, -0.5)] {
+            let v = target - o;
+            let (a, b) = (mesh.pdf_value(o, v), triangle.pdf_value(o, v));
+            assert!((a - b).abs() <= 1e-5 * b, "{a} != {b}");
+        }
+    }
+
+    #[test]
+    fn pdf_counts_every_face_along_the_direction() {
+        let material = Arc::new(Lambertian::new_color(Color::new(0.5, 0.5, 0.5)));
+        let layer = |y: f32| {
+            [
+                Point3::new(-1.0, y, -1.0),
+                Point3::new(1.0, y, -1.0),
+                Point3::new(0.0, y, 1.0),
+            ]
+        };
+        let (top, bottom) = (layer(0.0), layer(-1.0));
+        let mesh = TriangleMesh::new(
+            [top, bottom].concat(),
+            None,
+            None,
+            vec![[0, 1, 2], [3, 4, 5]],
+            material.clone(),
+        );
+        let [top, bottom] = [top, bottom].map(|[a, b, c]| Triangle::new(a, b, c, material.clone()));
+        let o = Point3::new(0.1, 2.0, 0.2);
+        let v = Point3::new(0.0, -1.0, 0.1) - o;
+        // Each face is half the mesh's area, so it's picked half as often.
+        let expected = 0.5 * (top.pdf_value(o, v) + bottom.pdf_value(o, v));
+        let pdf = mesh.pdf_value(o, v);
+        assert!(
+            (pdf - expected).abs() <= 1e-5 * expected,
+            "{pdf} != {expected}"
+        );
+    }
+}
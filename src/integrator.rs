@@ -0,0 +1,29 @@
+//! Estimators for the radiance arriving along a camera ray.
+
+use crate::hittable::Hittable;
+use crate::ray::Ray;
+use crate::Color;
+
+pub mod ao;
+pub mod normals;
+pub mod path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum IntegratorKind {
+    /// Unidirectional path tracing with next-event estimation.
+    Path,
+    /// Shading normals mapped to colors, for debugging geometry.
+    Normals,
+    /// Ambient occlusion.
+    Ao,
+}
+
+pub trait Integrator: Send + Sync {
+    fn li(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        background: Color,
+    ) -> Color;
+}
@@ -0,0 +1,38 @@
+use crate::hittable::Hittable;
+use crate::pdf::cosine::CosinePdf;
+use crate::pdf::Pdf;
+use crate::ray::Ray;
+use crate::sampler::{self, Decision};
+use crate::Color;
+
+use super::Integrator;
+
+/// White where a cosine-weighted direction from the first hit escapes
+/// within `distance`, black where it is blocked. Misses count as open.
+pub struct AoIntegrator {
+    pub distance: f32,
+}
+
+impl Integrator for AoIntegrator {
+    fn li(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _lights: &dyn Hittable,
+        _background: Color,
+    ) -> Color {
+        let rec = match world.hit(ray, 0.001, f32::INFINITY) {
+            Some(rec) => rec,
+            None => return Color::new(1.0, 1.0, 1.0),
+        };
+        sampler::start_bounce(0);
+        sampler::start_decision(Decision::Bsdf);
+        let direction = CosinePdf::new(&rec.normal).generate().normalize();
+        let occlusion_ray = Ray::new(rec.p, direction, ray.time);
+        if world.hit(&occlusion_ray, 0.001, self.distance).is_some() {
+            Color::zeros()
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use crate::hittable::Hittable;
+use crate::ray::Ray;
+use crate::Color;
+
+use super::Integrator;
+
+/// Maps the shading normal at the first hit from [-1, 1] to [0, 1] per
+/// channel; misses are black.
+pub struct NormalsIntegrator;
+
+impl Integrator for NormalsIntegrator {
+    fn li(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _lights: &dyn Hittable,
+        _background: Color,
+    ) -> Color {
+        match world.hit(ray, 0.001, f32::INFINITY) {
+            Some(rec) => 0.5 * (rec.normal + Color::new(1.0, 1.0, 1.0)),
+            None => Color::zeros(),
+        }
+    }
+}
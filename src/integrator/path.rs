@@ -0,0 +1,149 @@
+use crate::hittable::Hittable;
+use crate::material::{ScatterRecord, ScatterType};
+use crate::pdf::power_heuristic;
+use crate::ray::Ray;
+use crate::sampler::{self, Decision};
+use crate::vec3::Vec3;
+use crate::Color;
+
+use super::Integrator;
+
+/// Bounces after which a path with no `max_depth` is cut off anyway, so one
+/// that keeps winning the roulette in a closed scene still ends.
+pub const MAX_BOUNCES: usize = 1000;
+
+/// Traces a path with next-event estimation: each diffuse bounce samples a
+/// point on `lights` with a shadow ray as well as a direction from the
+/// material, and the two estimates of direct light are combined with the
+/// power heuristic.
+pub struct PathIntegrator {
+    /// Bounces after which a path is always cut off; 0 leaves it to Russian
+    /// roulette, up to `MAX_BOUNCES`.
+    pub max_depth: usize,
+    /// Bounces after which Russian roulette may end a path early.
+    pub roulette_depth: usize,
+}
+
+impl PathIntegrator {
+    /// Decides whether a path continues past `bounce` carrying `throughput`,
+    /// returning the factor to scale its throughput by.
+    ///
+    /// Once roulette starts, a path survives with probability equal to its
+    /// largest throughput component (capped at 0.95) and is reweighted by
+    /// the inverse, so dim paths end early without biasing the estimate.
+    fn survival(&self, bounce: usize, throughput: Color) -> Option<f32> {
+        let max_depth = match self.max_depth {
+            0 => MAX_BOUNCES,
+            depth => depth,
+        };
+        if bounce >= max_depth {
+            return None;
+        }
+        if bounce < self.roulette_depth {
+            return Some(1.0);
+        }
+        let p = throughput.max().min(0.95);
+        sampler::start_decision(Decision::Roulette);
+        (sampler::next_1d() < p).then(|| 1.0 / p)
+    }
+}
+
+impl Integrator for PathIntegrator {
+    fn li(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        background: Color,
+    ) -> Color {
+        let mut color = Color::zeros();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+        // The density the material sampled `ray` with, or `None` for camera
+        // rays and specular bounces, whose emission no light sample could
+        // have found.
+        let mut bsdf_pdf: Option<f32> = None;
+
+        for bounce in 0.. {
+            sampler::start_bounce(bounce);
+            let rec = match world.hit(&ray, 0.001, f32::INFINITY) {
+                Some(rec) => rec,
+                None => {
+                    color += throughput.component_mul(&background).into();
+                    break;
+                }
+            };
+
+            let mut emitted = rec.mat_ptr.emitted(&ray, &rec, rec.u, rec.v, rec.p);
+            if let Some(bsdf_pdf) = bsdf_pdf {
+                if !emitted.near_zero() {
+                    emitted = emitted
+                        * power_heuristic(bsdf_pdf, lights.pdf_value(ray.origin, ray.direction));
+                }
+            }
+            color += throughput.component_mul(&emitted).into();
+
+            let ScatterRecord {
+                attenuation,
+                scattered,
+            } = match rec.mat_ptr.scatter(&ray, &rec) {
+                Some(scatter) => scatter,
+                None => break,
+            };
+            if attenuation.near_zero() {
+                break;
+            }
+
+            match scattered {
+                ScatterType::Specular(specular_ray) => {
+                    throughput = throughput.component_mul(&attenuation).into();
+                    ray = specular_ray;
+                    bsdf_pdf = None;
+                }
+                ScatterType::Diffuse(pdf) => {
+                    sampler::start_decision(Decision::Light);
+                    let light_direction = lights.random(rec.p);
+                    let light_pdf = lights.pdf_value(rec.p, light_direction);
+                    if light_pdf > 0.0 {
+                        let shadow_ray = Ray::new(rec.p, light_direction, ray.time);
+                        if let Some(light_rec) = world.hit(&shadow_ray, 0.001, f32::INFINITY) {
+                            let light = light_rec.mat_ptr.emitted(
+                                &shadow_ray,
+                                &light_rec,
+                                light_rec.u,
+                                light_rec.v,
+                                light_rec.p,
+                            );
+                            if !light.near_zero() {
+                                let f = attenuation
+                                    * rec.mat_ptr.scattering_pdf(&ray, &rec, &shadow_ray);
+                                let weight = power_heuristic(light_pdf, pdf.value(light_direction));
+                                color +=
+                                    Vec3::from(throughput.component_mul(&f).component_mul(&light))
+                                        * (weight / light_pdf);
+                            }
+                        }
+                    }
+
+                    sampler::start_decision(Decision::Bsdf);
+                    let scattered = Ray::new(rec.p, pdf.generate(), ray.time);
+                    let pdf_val = pdf.value(scattered.direction);
+                    if pdf_val <= 0.0 {
+                        break;
+                    }
+                    let weight = attenuation
+                        * (rec.mat_ptr.scattering_pdf(&ray, &rec, &scattered) / pdf_val);
+                    throughput = throughput.component_mul(&weight).into();
+                    ray = scattered;
+                    bsdf_pdf = Some(pdf_val);
+                }
+            }
+
+            match self.survival(bounce + 1, throughput) {
+                Some(survival) => throughput = throughput * survival,
+                None => break,
+            }
+        }
+        color
+    }
+}
@@ -2,27 +2,38 @@
 #![allow(dead_code)]
 #![allow(clippy::too_many_arguments)]
 
-use std::time::Instant;
-use std::{f32, sync::Arc};
-
-use clap::Parser;
-use color::write_color;
-use hittable::Hittable;
-use image::RgbImage;
-use indicatif::ParallelProgressIterator;
-use material::{ScatterRecord, ScatterType};
-use pdf::mixture::MixturePdf;
-use pdf::{hittable::HittablePdf, Pdf};
+use std::collections::HashMap;
+use std::f32;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, ValueEnum};
 use rand::Rng;
-use ray::Ray;
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use sdl2::render::WindowCanvas;
 
-use crate::camera::Camera;
-use crate::hittable::aa_rect::XzRect;
-use crate::hittable::hittable_list::HittableList;
-use crate::hittable::sphere::Sphere;
-use crate::material::lambertian::Lambertian;
+use crate::adaptive::Adaptive;
+use crate::aov::{AovKind, MaterialIds};
+use crate::film::{Film, RenderSettings};
+use crate::filter::{Filter, FilterKind};
+use crate::hittable::bvh_node::{BvhNode, BvhOptions, SplitMethod};
+use crate::hittable::linear_bvh::LinearBvh;
+use crate::hittable::tagged::Tagged;
+use crate::hittable::Hittable;
+use crate::integrator::ao::AoIntegrator;
+use crate::integrator::normals::NormalsIntegrator;
+use crate::integrator::path::PathIntegrator;
+use crate::integrator::{Integrator, IntegratorKind};
+use crate::render::Renderer;
+use crate::sampler::halton::HaltonSampler;
+use crate::sampler::independent::IndependentSampler;
+use crate::sampler::pmj02::Pmj02Sampler;
+use crate::sampler::sobol::SobolSampler;
+use crate::sampler::stratified::StratifiedSampler;
+use crate::sampler::SamplerKind;
+use crate::tile::TileOrder;
+use crate::tonemap::{DisplayTransform, ToneMapper};
+use crate::viewer::SdlState;
 
 mod vec3;
 
@@ -31,22 +42,34 @@ type Point3 = Vec3;
 type Color = Vec3;
 
 mod aabb;
+mod adaptive;
+mod aov;
+mod bench;
 mod camera;
 mod color;
+mod denoise;
+mod film;
+mod filter;
 mod hittable;
+mod integrator;
 mod material;
+mod obj;
 mod onb;
 mod pdf;
 mod perlin;
 mod ray;
+mod rng;
+mod sampler;
 mod texture;
+mod tile;
+mod tonemap;
 
 fn random_vec() -> Vec3 {
     random_vec_range(0.0, 1.0)
 }
 
 fn random_vec_range(min: f32, max: f32) -> Vec3 {
-    let mut rng = rand::thread_rng();
+    let mut rng = rng::rng();
     Vec3::new(
         rng.gen_range(min..max),
         rng.gen_range(min..max),
@@ -76,394 +99,599 @@ fn random_unit_vector() -> Vec3 {
     random_vec_in_unit_sphere().normalize()
 }
 
-fn random_vec_in_unit_disk() -> Vec3 {
-    loop {
-        let p = Vec3::new(
-            rand::thread_rng().gen_range(-1.0..1.0),
-            rand::thread_rng().gen_range(-1.0..1.0),
-            0.0,
-        );
-        if p.dot(&p) < 1.0 {
-            return p;
-        }
-    }
-}
-
-fn ray_color(
-    r: &Ray,
-    background: Color,
-    world: &dyn Hittable,
-    lights: Arc<dyn Hittable>,
-    depth: usize,
-) -> Color {
-    if depth == 0 {
-        return Color::zeros();
-    }
-    if let Some(rec) = world.hit(r, 0.001, f32::INFINITY) {
-        let emitted = rec.mat_ptr.emitted(r, &rec, rec.u, rec.v, rec.p);
-        if let Some(ScatterRecord {
-            attenuation,
-            scattered,
-        }) = rec.mat_ptr.scatter(r, &rec)
-        {
-            if emitted.near_zero() && attenuation.near_zero() {
-                return Color::zeros();
-            }
-            match scattered {
-                ScatterType::Diffuse(pdf) => {
-                    let light_ptr = Arc::new(HittablePdf::new(lights.clone(), rec.p));
-                    let p = MixturePdf::new(light_ptr, pdf);
-
-                    let scattered = Ray::new(rec.p, p.generate(), r.time);
-                    let pdf_val = p.value(scattered.direction);
-                    return emitted
-                        + Vec3::from(
-                            (attenuation * rec.mat_ptr.scattering_pdf(r, &rec, &scattered))
-                                .component_mul(&ray_color(
-                                    &scattered,
-                                    background,
-                                    world,
-                                    lights,
-                                    depth - 1,
-                                )),
-                        ) / pdf_val;
-                }
-                ScatterType::Specular(specular_ray) => {
-                    return attenuation
-                        .component_mul(&ray_color(
-                            &specular_ray,
-                            background,
-                            world,
-                            lights,
-                            depth - 1,
-                        ))
-                        .into();
-                }
-            }
-        }
-        return emitted;
-    }
-    background
-}
-
 #[derive(Debug, clap::Parser)]
 struct Options {
-    /// Whether to render to a temporary window or to `output.png`.
+    #[clap(subcommand)]
+    command: Option<Command>,
+    /// Show the render refining in a window instead of writing `--output`;
+    /// press Ctrl+S in the window to write it anyway.
     #[clap(short, long)]
     live: bool,
-    /// The scene to render (1-8).
+    /// Where to write the image. `.exr`, `.hdr` and `.pfm` keep the full
+    /// dynamic range, and `.film` keeps the raw sums for `merge`; other
+    /// extensions such as `.png` are tone mapped.
+    #[clap(short, long, global = true, default_value = "output.png")]
+    output: PathBuf,
+    /// Image width in pixels [default: the scene's].
+    #[clap(long, value_parser(clap::value_parser!(u64).range(1..)))]
+    width: Option<u64>,
+    /// Image height in pixels; overrides the scene's aspect ratio
+    /// [default: the width divided by the aspect ratio].
+    #[clap(long, value_parser(clap::value_parser!(u64).range(1..)))]
+    height: Option<u64>,
+    /// Width-to-height ratio of the image [default: the scene's].
+    #[clap(long, conflicts_with = "height")]
+    aspect: Option<f32>,
+    /// Samples per pixel, or with `--adaptive` the most any pixel takes
+    /// [default: the scene's].
+    #[clap(long)]
+    spp: Option<usize>,
+    /// Stop sampling each pixel once its estimate is precise enough,
+    /// instead of giving every pixel `--spp` samples.
+    #[clap(long, conflicts_with = "live")]
+    adaptive: bool,
+    /// Relative standard error of a pixel's mean luminance at which
+    /// `--adaptive` stops sampling it.
+    #[clap(long, default_value_t = 0.02)]
+    adaptive_threshold: f64,
+    /// Samples every pixel takes with `--adaptive`, and how many more noisy
+    /// pixels take in each round after that.
+    #[clap(long, default_value_t = 16)]
+    min_spp: usize,
+    /// Also write an image of how many samples each pixel took, brighter
+    /// for more.
+    #[clap(long)]
+    heatmap: Option<PathBuf>,
+    /// Auxiliary buffers to write with the image, separated by commas:
+    /// extra layers of an `.exr` output, or files of their own beside any
+    /// other format.
+    #[clap(long, value_enum, value_delimiter = ',', conflicts_with = "live")]
+    aov: Vec<AovKind>,
+    /// Denoise the image with the help of the albedo, normal and depth
+    /// buffers, and keep the noisy original beside it as, say,
+    /// `out.noisy.png`.
+    #[clap(long, conflicts_with = "live")]
+    denoise: bool,
+    /// Seeds the random numbers of every sample, so the same seed always
+    /// gives the same image. Renders to be merged need different seeds.
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    /// How to distribute the samples of each pixel.
+    #[clap(long, value_enum, default_value_t = SamplerKind::Sobol)]
+    sampler: SamplerKind,
+    /// How to weight samples into the pixels around them.
+    #[clap(long, value_enum, default_value_t = FilterKind::Box)]
+    filter: FilterKind,
+    /// Radius of the filter in pixels [default: 0.5 for box, 1 for tent,
+    /// 1.5 for gaussian, 2 for mitchell and lanczos]. A box narrower than
+    /// 0.5 drops the samples that land nearer a pixel's edge than the
+    /// radius allows, wasting them.
+    #[clap(long, value_parser = parse_radius)]
+    filter_radius: Option<f32>,
+    /// Side of the square tiles each pass is split into, in pixels.
+    #[clap(long, default_value_t = 32, value_parser(clap::value_parser!(u64).range(1..)))]
+    tile_size: u64,
+    /// The order tiles are started in.
+    #[clap(long, value_enum, default_value_t = TileOrder::Spiral)]
+    tile_order: TileOrder,
+    /// Save the accumulated samples to this file as the render goes, so it
+    /// can be picked up with `--resume` if it stops.
+    #[clap(long, conflicts_with = "live")]
+    checkpoint: Option<PathBuf>,
+    /// Seconds between checkpoints.
+    #[clap(long, default_value_t = 300)]
+    checkpoint_interval: u64,
+    /// Continue the render saved in `--checkpoint` up to `--spp` samples.
+    /// The scene, seed, sampler and filter have to match the ones it was
+    /// started with, and `--spp` can't be lower.
+    #[clap(long, requires = "checkpoint")]
+    resume: bool,
+    /// Tone mapping operator for 8-bit output and the live window
+    /// [default: the scene's, or clamp].
+    #[clap(long, global = true, value_enum)]
+    tonemap: Option<ToneMapper>,
+    /// Exposure adjustment in stops before tone mapping [default: the
+    /// scene's, or 0].
+    #[clap(long, global = true, allow_negative_numbers = true)]
+    exposure: Option<f32>,
+    /// Luminance that `reinhard-extended` maps to white [default: the
+    /// scene's, or the brightest pixel].
+    #[clap(long, global = true)]
+    white_point: Option<f32>,
+    /// The built-in scene to render; see `--list-scenes`.
     #[clap(
         short,
         long,
-        value_parser(clap::value_parser!(u64).range(1..=8)),
-        default_value_t = 8
+        value_parser = clap::builder::PossibleValuesParser::new(scenes::SCENES.iter().map(|s| s.name)),
+        default_value = "final-scene"
     )]
-    scene: u64,
+    scene: String,
+    /// Print the built-in scenes and exit.
+    #[clap(long)]
+    list_scenes: bool,
+    /// Load the scene from a TOML description instead of a built-in scene.
+    #[clap(long, conflicts_with = "scene")]
+    scene_file: Option<PathBuf>,
+    /// How to split the scene's bounding volume hierarchy.
+    #[clap(long, value_enum, default_value_t = SplitMethod::Sah)]
+    bvh: SplitMethod,
+    /// Largest number of objects the BVH builder may leave in one leaf.
+    #[clap(long, default_value_t = 4)]
+    bvh_leaf_size: usize,
+    /// Print the BVH's build time, shape and SAH cost.
+    #[clap(long)]
+    bvh_stats: bool,
+    /// Time the flattened BVH against the recursive one on this scene, then
+    /// exit without rendering.
+    #[clap(long)]
+    bvh_bench: bool,
+    /// How to estimate the light along each camera ray.
+    #[clap(long, value_enum, default_value_t = IntegratorKind::Path)]
+    integrator: IntegratorKind,
+    /// How far the `ao` integrator looks for occluders.
+    #[clap(long, default_value_t = 1.0)]
+    ao_distance: f32,
+    /// Most bounces a path may take; 0 leaves Russian roulette to end
+    /// paths, up to 1000 bounces [default: the scene's, or 50].
+    #[clap(long)]
+    max_depth: Option<usize>,
+    /// Bounces before Russian roulette starts ending low-throughput paths.
+    #[clap(long, default_value_t = 3)]
+    roulette_depth: usize,
 }
 
-struct SdlState {
-    canvas: WindowCanvas,
-    event_pump: sdl2::EventPump,
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Add up `.film` outputs from independent renders of the same scene and
+    /// write the result to `--output`.
+    Merge {
+        #[clap(required = true)]
+        inputs: Vec<PathBuf>,
+    },
 }
 
-enum State {
-    Online(SdlState),
-    Offline,
+impl Options {
+    /// Replaces the parts of `display` given on the command line.
+    fn override_display(&self, display: &mut DisplayTransform) {
+        if let Some(tonemap) = self.tonemap {
+            display.tonemap = tonemap;
+        }
+        if let Some(exposure) = self.exposure {
+            display.exposure = exposure;
+        }
+        if self.white_point.is_some() {
+            display.white_point = self.white_point;
+        }
+    }
 }
 
+mod render;
+mod scene_file;
 mod scenes;
+mod viewer;
 
 fn main() {
-    let mut aspect_ratio = 16.0 / 9.0;
-    let mut image_width = 400;
-    const max_depth: usize = 50;
-
-    let lookfrom = Point3::new(13.0, 2.0, 3.0);
-    let lookat = Point3::new(0.0, 0.0, 0.0);
-    let vup = Vec3::new(0.0, 1.0, 0.0);
-    let vfov = 20.0;
-    let dist_to_focus = 10.0;
-    let aperture = 0.0;
-
-    let mut background = Color::new(0.0, 0.0, 0.0);
-    let mut samples_per_pixel = 100;
-    let mut lights: Arc<dyn Hittable> = Arc::new(HittableList::new());
-
     let options = Options::parse();
-
-    let (camera, world) = match options.scene {
-        1 => {
-            background = Color::new(0.7, 0.8, 1.0);
-            (
-                Camera::new(
-                    lookfrom,
-                    lookat,
-                    vup,
-                    vfov,
-                    aspect_ratio,
-                    aperture,
-                    dist_to_focus,
-                    0.0,
-                    1.0,
-                ),
-                scenes::random_scene(),
-            )
-        }
-        2 => {
-            background = Color::new(0.7, 0.8, 1.0);
-            (
-                Camera::new(
-                    lookfrom,
-                    lookat,
-                    vup,
-                    vfov,
-                    aspect_ratio,
-                    aperture,
-                    dist_to_focus,
-                    0.0,
-                    1.0,
-                ),
-                scenes::two_spheres(),
+    if options.output.extension().is_none() {
+        Options::command()
+            .error(
+                ErrorKind::ValueValidation,
+                "--output needs an extension, such as .png, to pick the format by",
             )
-        }
-        3 => {
-            background = Color::new(0.7, 0.8, 1.0);
-            (
-                Camera::new(
-                    lookfrom,
-                    lookat,
-                    vup,
-                    vfov,
-                    aspect_ratio,
-                    aperture,
-                    dist_to_focus,
-                    0.0,
-                    1.0,
-                ),
-                scenes::two_perlin_spheres(),
-            )
-        }
-        4 => {
-            background = Color::new(0.7, 0.8, 1.0);
-            (
-                Camera::new(
-                    lookfrom,
-                    lookat,
-                    vup,
-                    vfov,
-                    aspect_ratio,
-                    aperture,
-                    dist_to_focus,
-                    0.0,
-                    1.0,
-                ),
-                scenes::earth(),
-            )
-        }
-        5 => {
-            samples_per_pixel = 400;
-            let lookfrom = Point3::new(26.0, 3.0, 6.0);
-            let lookat = Point3::new(0.0, 2.0, 0.0);
-            (
-                Camera::new(
-                    lookfrom,
-                    lookat,
-                    vup,
-                    vfov,
-                    aspect_ratio,
-                    aperture,
-                    dist_to_focus,
-                    0.0,
-                    1.0,
-                ),
-                scenes::simple_light(),
-            )
-        }
-        6 => {
-            aspect_ratio = 1.0;
-            image_width = 600;
-            samples_per_pixel = 1000;
-            let lookfrom = Point3::new(278.0, 278.0, -800.0);
-            let lookat = Point3::new(278.0, 278.0, 0.0);
-            let vfov = 40.0;
-            let mut light_list = HittableList::new();
-            light_list.add(Arc::new(XzRect::new(
-                213.0,
-                343.0,
-                227.0,
-                332.0,
-                554.0,
-                Arc::new(Lambertian::new_color(Color::new(0.0, 0.0, 0.0))),
-            )));
-            light_list.add(Arc::new(Sphere::new(
-                Point3::new(190.0, 90.0, 190.0),
-                90.0,
-                Arc::new(Lambertian::new_color(Color::zeros())),
-            )));
-            lights = Arc::new(light_list);
-            (
-                Camera::new(
-                    lookfrom,
-                    lookat,
-                    vup,
-                    vfov,
-                    aspect_ratio,
-                    aperture,
-                    dist_to_focus,
-                    0.0,
-                    1.0,
-                ),
-                scenes::cornell_box(),
-            )
-        }
-        7 => {
-            aspect_ratio = 1.0;
-            image_width = 600;
-            samples_per_pixel = 200;
-            let lookfrom = Point3::new(278.0, 278.0, -800.0);
-            let lookat = Point3::new(278.0, 278.0, 0.0);
-            let vfov = 40.0;
-            let mut light_list = HittableList::new();
-            light_list.add(Arc::new(XzRect::new(
-                213.0,
-                343.0,
-                227.0,
-                332.0,
-                554.0,
-                Arc::new(Lambertian::new_color(Color::new(0.0, 0.0, 0.0))),
-            )));
-            lights = Arc::new(light_list);
-            (
-                Camera::new(
-                    lookfrom,
-                    lookat,
-                    vup,
-                    vfov,
-                    aspect_ratio,
-                    aperture,
-                    dist_to_focus,
-                    0.0,
-                    1.0,
-                ),
-                scenes::cornell_smoke(),
-            )
-        }
-        8 => {
-            aspect_ratio = 1.0;
-            image_width = 800;
-            samples_per_pixel = 10000;
-            let lookfrom = Point3::new(478.0, 278.0, -600.0);
-            let lookat = Point3::new(278.0, 278.0, 0.0);
-            let vfov = 40.0;
-            let mut light_list = HittableList::new();
-            light_list.add(Arc::new(XzRect::new(
-                123.0,
-                423.0,
-                147.0,
-                412.0,
-                554.0,
-                Arc::new(Lambertian::new_color(Color::new(0.0, 0.0, 0.0))),
-            )));
-            lights = Arc::new(light_list);
-            (
-                Camera::new(
-                    lookfrom,
-                    lookat,
-                    vup,
-                    vfov,
-                    aspect_ratio,
-                    aperture,
-                    dist_to_focus,
-                    0.0,
-                    1.0,
-                ),
-                scenes::final_scene(),
+            .exit();
+    }
+
+    if let Some(Command::Merge { inputs }) = &options.command {
+        let mut display = DisplayTransform::default();
+        options.override_display(&mut display);
+        merge(inputs, &options.output, &display);
+        return;
+    }
+
+    // A film keeps sums of samples, and the denoised image has none to
+    // merge with others or resume from.
+    let is_film = |path: &Path| path.extension().is_some_and(|e| e == "film");
+    if options.denoise && is_film(&options.output) {
+        Options::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--denoise can't write a .film; save the image in another format",
             )
+            .exit();
+    }
+
+    if options.list_scenes {
+        for scene in scenes::SCENES {
+            println!("{:<20} {}", scene.name, scene.description);
         }
-        _ => unreachable!(),
+        return;
+    }
+
+    // Random scenes are laid out the same whatever `--seed` is, so renders
+    // with different seeds can still be merged.
+    rng::seed(0);
+    let scene = if let Some(path) = &options.scene_file {
+        scene_file::load(path)
+    } else {
+        let info = scenes::find(&options.scene).expect("clap only accepts registered scenes");
+        (info.build)()
+    };
+    let mut aspect_ratio = scene.render.aspect_ratio;
+    let mut image_width = scene.render.image_width;
+    let mut samples_per_pixel = scene.render.samples_per_pixel;
+    let mut max_depth = scene.render.max_depth;
+    let mut display = DisplayTransform {
+        tonemap: scene.render.tonemap,
+        exposure: scene.render.exposure,
+        white_point: scene.render.white_point,
     };
+    let background = scene.background;
+    let lights = scene.lights;
+    let world = scene.world;
 
-    let image_height = (image_width as f32 / aspect_ratio) as usize;
+    if let Some(aspect) = options.aspect {
+        aspect_ratio = aspect;
+    }
+    if let Some(width) = options.width {
+        image_width = width as usize;
+    }
+    let image_height = match options.height {
+        Some(height) => height as usize,
+        None => ((image_width as f32 / aspect_ratio) as usize).max(1),
+    };
+    // Rounding the height off can change the shape slightly, and the camera
+    // has to match the pixels or the image comes out stretched.
+    let aspect_ratio = image_width as f32 / image_height as f32;
+    let camera = scene.camera.build(aspect_ratio);
+    if let Some(spp) = options.spp {
+        samples_per_pixel = spp;
+    }
+    if let Some(depth) = options.max_depth {
+        max_depth = depth;
+    }
+    options.override_display(&mut display);
 
-    let state = if options.live {
-        let sdl = sdl2::init().unwrap();
-        let video = sdl.video().unwrap();
-        let window = video
-            .window(
-                "Ray Tracing in One Weekend",
-                image_width as u32,
-                image_height as u32,
-            )
-            .build()
-            .unwrap()
-            .into_canvas()
-            .present_vsync()
-            .build()
-            .unwrap();
-        let event_pump = sdl.event_pump().unwrap();
-        State::Online(SdlState {
-            canvas: window,
-            event_pump,
-        })
+    let bvh_options = BvhOptions {
+        split: options.bvh,
+        max_leaf_size: options.bvh_leaf_size.max(1),
+        ..Default::default()
+    };
+    let integrator: Box<dyn Integrator> = match options.integrator {
+        IntegratorKind::Path => Box::new(PathIntegrator {
+            max_depth,
+            roulette_depth: options.roulette_depth,
+        }),
+        IntegratorKind::Normals => Box::new(NormalsIntegrator),
+        IntegratorKind::Ao => Box::new(AoIntegrator {
+            distance: options.ao_distance,
+        }),
+    };
+
+    if options.bvh_bench {
+        bench::run(&camera, &world, &bvh_options, image_width, image_height);
+        return;
+    }
+
+    let material_ids = MaterialIds::new(&world);
+    let build_start = Instant::now();
+    let objects = if options.aov.is_empty() {
+        world.children().to_vec()
     } else {
-        State::Offline
+        // Number the scene's objects for the object ID buffer.
+        (1..)
+            .zip(world.children())
+            .map(|(id, object)| Arc::new(Tagged::new(object.clone(), id)) as Arc<dyn Hittable>)
+            .collect()
+    };
+    let tree = BvhNode::with_options(&objects, 0.0, 1.0, &bvh_options);
+    if options.bvh_stats {
+        println!(
+            "Built {:?} BVH in {} seconds: {}",
+            options.bvh,
+            build_start.elapsed().as_secs_f32(),
+            tree.stats()
+        );
+    }
+    let world = LinearBvh::from(tree);
+
+    let mut renderer = Renderer {
+        camera,
+        world: &world,
+        lights: &*lights,
+        background,
+        integrator: &*integrator,
+        filter: Filter {
+            kind: options.filter,
+            radius: options
+                .filter_radius
+                .unwrap_or_else(|| options.filter.default_radius()),
+        },
+        width: image_width,
+        height: image_height,
+        tiles: tile::tiles(
+            image_width,
+            image_height,
+            options.tile_size as usize,
+            options.tile_order,
+        ),
+        seed: options.seed,
+        sampler: match options.sampler {
+            SamplerKind::Independent => Arc::new(IndependentSampler::new(options.seed)),
+            SamplerKind::Stratified => {
+                Arc::new(StratifiedSampler::new(options.seed, samples_per_pixel))
+            }
+            SamplerKind::Halton => Arc::new(HaltonSampler::new(options.seed)),
+            SamplerKind::Sobol => Arc::new(SobolSampler::new(options.seed)),
+            SamplerKind::Pmj02 => Arc::new(Pmj02Sampler::new(options.seed)),
+        },
+    };
+    let settings = RenderSettings {
+        scene: match &options.scene_file {
+            Some(path) => path.display().to_string(),
+            None => options.scene.clone(),
+        },
+        seeds: vec![options.seed],
+        sampler: value_name(options.sampler),
+        filter: value_name(options.filter),
+        filter_radius: renderer.filter.radius,
+        samples_per_pixel: samples_per_pixel as u32,
     };
+    let mut film = Film::new(image_width, image_height);
+    film.set_settings(settings.clone());
+
+    if options.live {
+        let state = SdlState::new(image_width, image_height);
+        viewer::run(
+            state,
+            &mut renderer,
+            scene.camera,
+            aspect_ratio,
+            &mut film,
+            samples_per_pixel,
+            &display,
+            &options.output,
+        );
+        return;
+    }
 
-    let mut img = RgbImage::new(image_width as u32, image_height as u32);
+    if options.resume {
+        let path = options.checkpoint.as_ref().unwrap();
+        film = Film::load_checkpoint(path)
+            .unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display()));
+        if (film.width(), film.height()) != (image_width, image_height) {
+            panic!(
+                "{} is a {}x{} render, not {image_width}x{image_height}",
+                path.display(),
+                film.width(),
+                film.height()
+            );
+        }
+        check_resume(path, &film, &settings);
+        // Later checkpoints record the new target.
+        film.set_settings(settings);
+    }
+    let checkpoint = |film: &Film| {
+        if let Some(path) = &options.checkpoint {
+            film.save_checkpoint(path)
+                .unwrap_or_else(|e| panic!("failed to write {}: {e}", path.display()));
+        }
+    };
 
     let render_start = Instant::now();
-    let (tx, rx) = crossbeam_channel::unbounded::<(usize, usize, Color)>();
-    (0..image_height)
-        .into_par_iter()
-        .progress_with_style(
-            indicatif::ProgressStyle::default_bar()
-                .progress_chars("█▉▊▋▌▍▎▏ ")
-                .template("[{elapsed_precise}/{duration_precise}] [{wide_bar}] {pos:>7}/{len:7} ({percent:>3}%) {msg}")
-                .unwrap(),
-        )
-        .with_message(format!("Rendering {image_width}x{image_height}"))
-        .for_each_with(tx, |tx, j| {
-            for i in 0..image_width {
-                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _ in 0..samples_per_pixel {
-                    let u = (i as f32 + rand::thread_rng().gen_range(0.0..1.0))
-                        / (image_width - 1) as f32;
-                    let v = (j as f32 + rand::thread_rng().gen_range(0.0..1.0))
-                        / (image_height - 1) as f32;
-                    let r = camera.get_ray(u, v);
-                    pixel_color += ray_color(&r, background, world.as_ref(), lights.clone(), max_depth);
+    let adaptive = options.adaptive.then(|| Adaptive {
+        min_samples: options.min_spp.max(1),
+        max_samples: samples_per_pixel,
+        threshold: options.adaptive_threshold,
+    });
+    let interval = Duration::from_secs(options.checkpoint_interval);
+    let mut last_checkpoint = Instant::now();
+    // Each round takes up where the film leaves off, so a resumed render
+    // tops every pixel up with the samples an uninterrupted render would
+    // have taken next. Without `--adaptive` the first round finishes.
+    for round in 1.. {
+        let remaining = match &adaptive {
+            Some(adaptive) => adaptive.next_samples(&film),
+            None => (0..image_height)
+                .flat_map(|y| (0..image_width).map(move |x| (x, y)))
+                .map(|(x, y)| film.samples(x, y).min(samples_per_pixel)..samples_per_pixel)
+                .collect(),
+        };
+        let active = remaining.iter().filter(|r| !r.is_empty()).count();
+        if active == 0 {
+            break;
+        }
+        let mut message = format!("Rendering {image_width}x{image_height}");
+        if adaptive.is_some() {
+            message += &format!(", round {round}: {active} pixels");
+        }
+        renderer.accumulate(
+            &mut film,
+            |x, y| remaining[y * image_width + x].clone(),
+            render::progress_bar(renderer.tiles.len(), message),
+            |film| {
+                if last_checkpoint.elapsed() >= interval {
+                    checkpoint(film);
+                    last_checkpoint = Instant::now();
                 }
-                tx.send((i, image_height - j - 1, pixel_color)).unwrap();
-            }
-        });
-    while let Ok((x, y, color)) = rx.recv() {
-        write_color(&mut img, (x, y), &color, samples_per_pixel);
+            },
+        );
     }
+    checkpoint(&film);
     let render_end = Instant::now();
     let render_time = render_end - render_start;
     println!("Rendering took {} seconds", render_time.as_secs_f32());
 
-    match state {
-        State::Online(mut state) => {
-            let texture_creator = state.canvas.texture_creator();
-            let mut texture = texture_creator
-                .create_texture_streaming(
-                    sdl2::pixels::PixelFormatEnum::RGB24,
-                    image_width as u32,
-                    image_height as u32,
-                )
-                .unwrap();
-            texture.update(None, &img, image_width * 3).unwrap();
-            'sdl_loop: loop {
-                for event in state.event_pump.poll_iter() {
-                    if let sdl2::event::Event::Quit { .. } = event {
-                        break 'sdl_loop;
-                    }
-                }
-                state.canvas.copy(&texture, None, None).unwrap();
-                state.canvas.present();
+    if adaptive.is_some() {
+        let total = (0..image_height)
+            .flat_map(|y| (0..image_width).map(move |x| (x, y)))
+            .map(|(x, y)| film.samples(x, y))
+            .sum::<usize>();
+        println!(
+            "Took {:.1} samples per pixel on average",
+            total as f32 / (image_width * image_height) as f32
+        );
+    }
+
+    if options.aov.is_empty() && !options.denoise {
+        film.save(&options.output, &display)
+    } else {
+        let aovs = renderer.trace_aovs(
+            &material_ids,
+            samples_per_pixel.min(aov::MAX_SAMPLES),
+            render::progress_bar(image_height, "Tracing AOVs".to_string()),
+        );
+        let denoised = options.denoise.then(|| {
+            let noisy = film::tagged_path(&options.output, "noisy");
+            film.save(&noisy, &display)
+                .unwrap_or_else(|e| panic!("failed to write {}: {e}", noisy.display()));
+            let denoise_start = Instant::now();
+            let denoised = denoise::denoise(&film, &aovs);
+            println!(
+                "Denoising took {} seconds",
+                denoise_start.elapsed().as_secs_f32()
+            );
+            denoised
+        });
+        let image = denoised.as_ref().unwrap_or(&film);
+        aovs.save(&options.aov, image, &options.output, &display)
+    }
+    .unwrap_or_else(|e| panic!("failed to write {}: {e}", options.output.display()));
+    if let Some(path) = &options.heatmap {
+        adaptive::heatmap(&film)
+            .save(path)
+            .unwrap_or_else(|e| panic!("failed to write {}: {e}", path.display()));
+    }
+}
+
+/// Parses a filter radius, which has to be positive and finite for the
+/// filter to give any pixel a weight.
+fn parse_radius(s: &str) -> Result<f32, String> {
+    let radius = s.parse::<f32>().map_err(|e| e.to_string())?;
+    if radius > 0.0 && radius.is_finite() {
+        Ok(radius)
+    } else {
+        Err(format!("{radius} is not a positive number of pixels"))
+    }
+}
+
+/// What `value` is called on the command line.
+fn value_name(value: impl ValueEnum) -> String {
+    value.to_possible_value().unwrap().get_name().to_string()
+}
+
+/// The settings of the film at `path`, which every film a render writes
+/// has.
+fn saved_settings<'a>(path: &Path, film: &'a Film) -> &'a RenderSettings {
+    film.settings()
+        .unwrap_or_else(|| panic!("{} doesn't record how it was rendered", path.display()))
+}
+
+/// How `saved` differs from `settings` in what decides which samples a
+/// render takes, such as `--sampler box, not tent`, leaving out the seeds
+/// and sample counts.
+fn differences(saved: &RenderSettings, settings: &RenderSettings) -> Vec<String> {
+    [
+        ("scene", saved.scene.clone(), settings.scene.clone()),
+        ("--sampler", saved.sampler.clone(), settings.sampler.clone()),
+        ("--filter", saved.filter.clone(), settings.filter.clone()),
+        (
+            "--filter-radius",
+            saved.filter_radius.to_string(),
+            settings.filter_radius.to_string(),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, saved, current)| saved != current)
+    .map(|(name, saved, current)| format!("{name} {saved}, not {current}"))
+    .collect()
+}
+
+/// Panics unless the checkpoint `film` at `path` can be carried on with
+/// `settings`: the same scene, seed, sampler and filter, and at least as
+/// many samples per pixel. The stratified sampler lays its grid out for one
+/// sample count, so it needs exactly the same one.
+fn check_resume(path: &Path, film: &Film, settings: &RenderSettings) {
+    let saved = saved_settings(path, film);
+    let mut differences = differences(saved, settings);
+    if saved.seeds != settings.seeds {
+        let seeds = |s: &RenderSettings| {
+            s.seeds
+                .iter()
+                .map(u64::to_string)
+                .collect::<Vec<_>>()
+                .join("+")
+        };
+        differences.push(format!("--seed {}, not {}", seeds(saved), seeds(settings)));
+    }
+    if !differences.is_empty() {
+        panic!(
+            "{} was rendered with {}",
+            path.display(),
+            differences.join(", ")
+        );
+    }
+    let stratified = settings.sampler == "stratified";
+    if settings.samples_per_pixel < saved.samples_per_pixel
+        || stratified && settings.samples_per_pixel != saved.samples_per_pixel
+    {
+        panic!(
+            "{} was started for {} samples per pixel; resume it with --spp {}{}",
+            path.display(),
+            saved.samples_per_pixel,
+            saved.samples_per_pixel,
+            if stratified { "" } else { " or more" }
+        );
+    }
+}
+
+/// Sums the films in `inputs` into one image as if it had been rendered
+/// with all of their samples at once. They must be renders of the same
+/// size, scene, sampler and filter with different seeds; two films with the
+/// same seed hold the same samples, and summing them would only count each
+/// sample twice.
+fn merge(inputs: &[PathBuf], output: &Path, display: &DisplayTransform) {
+    let load = |path: &PathBuf| {
+        Film::load_checkpoint(path)
+            .unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display()))
+    };
+    let mut film = load(&inputs[0]);
+    let mut seeds = HashMap::new();
+    for &seed in &saved_settings(&inputs[0], &film).seeds {
+        seeds.insert(seed, &inputs[0]);
+    }
+    for path in &inputs[1..] {
+        let other = load(path);
+        if (other.width(), other.height()) != (film.width(), film.height()) {
+            panic!(
+                "{} is {}x{} but {} is {}x{}",
+                path.display(),
+                other.width(),
+                other.height(),
+                inputs[0].display(),
+                film.width(),
+                film.height()
+            );
+        }
+        let settings = saved_settings(path, &other);
+        let differences = differences(settings, saved_settings(&inputs[0], &film));
+        if !differences.is_empty() {
+            panic!(
+                "{} was rendered with {}",
+                path.display(),
+                differences.join(", ")
+            );
+        }
+        for &seed in &settings.seeds {
+            if let Some(first) = seeds.insert(seed, path) {
+                panic!(
+                    "{} and {} were both rendered with --seed {seed}, so they hold the same samples",
+                    first.display(),
+                    path.display()
+                );
             }
         }
-        State::Offline => img.save("output.png").unwrap(),
+        film.add_film(&other);
     }
+    film.save(output, display)
+        .unwrap_or_else(|e| panic!("failed to write {}: {e}", output.display()));
 }
@@ -30,4 +30,9 @@ pub trait Material: Send + Sync {
     fn emitted(&self, _r_in: &Ray, _rec: &HitRecord, _u: f32, _v: f32, _p: Point3) -> Color {
         Color::zeros()
     }
+    /// The surface's color, for the albedo buffer: how much light it
+    /// reflects or transmits overall, with emitters clipped to white.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::zeros()
+    }
 }
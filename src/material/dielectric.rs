@@ -4,6 +4,7 @@ use crate::hittable::HitRecord;
 use crate::material::Material;
 use crate::material::ScatterRecord;
 use crate::ray::Ray;
+use crate::rng;
 use crate::Color;
 
 use super::ScatterType;
@@ -40,7 +41,7 @@ impl Material for Dielectric {
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
 
         let direction = if cannot_refract
-            || reflectance(cos_theta, refraction_ratio) > rand::thread_rng().gen_range(0.0..1.0)
+            || reflectance(cos_theta, refraction_ratio) > rng::rng().gen_range(0.0..1.0)
         {
             unit_direction.reflect(&rec.normal)
         } else {
@@ -53,4 +54,8 @@ impl Material for Dielectric {
             scattered,
         })
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
 }
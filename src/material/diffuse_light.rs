@@ -36,4 +36,11 @@ impl Material for DiffuseLight {
             Color::zeros()
         }
     }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.emit
+            .value(rec.u, rec.v, rec.p)
+            .map(|c| c.min(1.0))
+            .into()
+    }
 }
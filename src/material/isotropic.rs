@@ -1,7 +1,8 @@
+use std::f32;
 use std::sync::Arc;
 
 use crate::hittable::HitRecord;
-use crate::random_vec_in_unit_sphere;
+use crate::pdf::sphere::SpherePdf;
 use crate::ray::Ray;
 use crate::texture::solid_color::SolidColor;
 use crate::texture::Texture;
@@ -26,14 +27,18 @@ impl Isotropic {
 }
 
 impl Material for Isotropic {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(&self, _ray: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
         Some(ScatterRecord {
             attenuation: self.albedo.value(rec.u, rec.v, rec.p),
-            scattered: ScatterType::Specular(Ray::new(
-                rec.p,
-                random_vec_in_unit_sphere(),
-                ray.time,
-            )),
+            scattered: ScatterType::Diffuse(Arc::new(SpherePdf)),
         })
     }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f32 {
+        1.0 / (4.0 * f32::consts::PI)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.value(rec.u, rec.v, rec.p)
+    }
 }
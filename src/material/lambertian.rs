@@ -4,6 +4,7 @@ use std::sync::Arc;
 use rand::Rng;
 
 use crate::pdf::cosine::CosinePdf;
+use crate::rng;
 use crate::texture::solid_color::SolidColor;
 use crate::texture::Texture;
 use crate::vec3::Vec3;
@@ -28,8 +29,8 @@ impl Lambertian {
 }
 
 fn random_cosine_direction() -> Vec3 {
-    let r1 = rand::thread_rng().gen_range(0.0f32..1.0);
-    let r2 = rand::thread_rng().gen_range(0.0f32..1.0);
+    let r1 = rng::rng().gen_range(0.0f32..1.0);
+    let r2 = rng::rng().gen_range(0.0f32..1.0);
     let z = (1.0 - r2).sqrt();
 
     let phi = 2.0 * f32::consts::PI * r1;
@@ -55,4 +56,8 @@ impl Material for Lambertian {
             cosine / f32::consts::PI
         }
     }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.value(rec.u, rec.v, rec.p)
+    }
 }
@@ -23,8 +23,12 @@ impl Material for Metal {
             scattered: ScatterType::Specular(Ray::new(
                 rec.p,
                 reflected + self.fuzz * random_vec_in_unit_sphere(),
-                0.0,
+                r_in.time,
             )),
         })
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
 }
@@ -0,0 +1,193 @@
+//! Wavefront OBJ loading, with MTL materials mapped onto our own.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::hittable::hittable_list::HittableList;
+use crate::hittable::triangle_mesh::TriangleMesh;
+use crate::material::dielectric::Dielectric;
+use crate::material::diffuse_light::DiffuseLight;
+use crate::material::lambertian::Lambertian;
+use crate::material::metal::Metal;
+use crate::material::Material;
+use crate::texture::image::ImageTexture;
+use crate::{Color, Point3, Vec3};
+
+/// Loads every model in the OBJ file as a `TriangleMesh`. Faces without an
+/// MTL material use `default_material`.
+pub fn load(path: &Path, default_material: Arc<dyn Material>) -> HittableList {
+    let (models, materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)
+        .unwrap_or_else(|e| panic!("failed to load {}: {e}", path.display()));
+    let materials = match materials {
+        Ok(materials) => materials,
+        Err(e) => {
+            eprintln!("warning: ignoring materials of {}: {e}", path.display());
+            Vec::new()
+        }
+    };
+
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let materials = materials
+        .iter()
+        .map(|m| convert_material(m, base_dir))
+        .collect::<Vec<_>>();
+
+    let mut objects = HittableList::new();
+    for model in models {
+        let mesh = model.mesh;
+        if mesh.indices.is_empty() {
+            continue;
+        }
+        let positions = mesh
+            .positions
+            .chunks_exact(3)
+            .map(|p| Point3::new(p[0], p[1], p[2]))
+            .collect();
+        let normals = (!mesh.normals.is_empty()).then(|| {
+            mesh.normals
+                .chunks_exact(3)
+                .map(|n| Vec3::new(n[0], n[1], n[2]))
+                .collect()
+        });
+        let uvs = (!mesh.texcoords.is_empty()).then(|| {
+            mesh.texcoords
+                .chunks_exact(2)
+                .map(|t| (t[0], t[1]))
+                .collect()
+        });
+        let indices = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|i| [i[0] as usize, i[1] as usize, i[2] as usize])
+            .collect();
+        let material = mesh
+            .material_id
+            .and_then(|id| materials.get(id).cloned())
+            .unwrap_or_else(|| default_material.clone());
+
+        objects.add(Arc::new(TriangleMesh::new(
+            positions, normals, uvs, indices, material,
+        )));
+    }
+    objects
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let mut parts = value.split_whitespace().map(|p| p.parse::<f32>());
+    let r = parts.next()?.ok()?;
+    // A single value is a grey level, as for `Kd`.
+    let g = parts.next().map_or(Some(r), Result::ok)?;
+    let b = parts.next().map_or(Some(g), Result::ok)?;
+    Some(Color::new(r, g, b))
+}
+
+/// Picks the closest of our materials for an MTL material:
+///
+/// - `Ke` (emission) becomes `DiffuseLight`,
+/// - transparent materials (`d` < 1 or a refractive `illum`) become
+///   `Dielectric` with `Ni` as the index of refraction,
+/// - materials with `Ks` and either no diffuse color or the reflective
+///   `illum 3` become `Metal`, with `Ns` mapped to fuzz,
+/// - everything else is `Lambertian` with `Kd` or `map_Kd`.
+fn convert_material(m: &tobj::Material, base_dir: &Path) -> Arc<dyn Material> {
+    let emission = m
+        .unknown_param
+        .get("Ke")
+        .and_then(|ke| parse_color(ke))
+        .unwrap_or(Color::zeros());
+    if !emission.near_zero() {
+        return Arc::new(DiffuseLight::new_color(emission));
+    }
+
+    let illum = m.illumination_model.unwrap_or(2);
+    if m.dissolve < 1.0 || matches!(illum, 4 | 6 | 7 | 9) {
+        let ref_idx = if m.optical_density > 0.0 {
+            m.optical_density
+        } else {
+            1.5
+        };
+        return Arc::new(Dielectric::new(ref_idx));
+    }
+
+    let diffuse = Color::from(m.diffuse);
+    let specular = Color::from(m.specular);
+    if !specular.near_zero() && (illum == 3 || diffuse.near_zero()) {
+        // Ns runs from 0 (rough) to 1000 (mirror).
+        let fuzz = 1.0 - (m.shininess / 1000.0).clamp(0.0, 1.0);
+        return Arc::new(Metal::new(specular, fuzz));
+    }
+
+    if !m.diffuse_texture.is_empty() {
+        let texture_path = base_dir.join(&m.diffuse_texture);
+        match File::open(&texture_path) {
+            Ok(file) => {
+                let texture = ImageTexture::new(&mut BufReader::new(file));
+                return Arc::new(Lambertian::new(Arc::new(texture)));
+            }
+            Err(e) => eprintln!(
+                "warning: failed to open texture {}: {e}",
+                texture_path.display()
+            ),
+        }
+    }
+    Arc::new(Lambertian::new_color(diffuse))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+    use crate::hittable::Hittable;
+    use crate::ray::Ray;
+
+    /// Writes `source` to a scratch OBJ file and loads it.
+    fn load_source(name: &str, source: &str) -> HittableList {
+        let path = std::env::temp_dir().join(format!("rt-{name}-{}.obj", std::process::id()));
+        std::fs::write(&path, source).unwrap();
+        let material = Arc::new(Lambertian::new_color(Color::new(0.5, 0.5, 0.5)));
+        let loaded = panic::catch_unwind(AssertUnwindSafe(|| load(&path, material)));
+        std::fs::remove_file(&path).unwrap();
+        loaded.unwrap_or_else(|e| panic::resume_unwind(e))
+    }
+
+    #[test]
+    fn loads_normals_and_uvs() {
+        let objects = load_source(
+            "triangle",
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+             vt 0.5 0.5\nvt 1 0.5\nvt 0.5 1\n\
+             vn 0 0.6 0.8\n\
+             f 1/1/1 2/2/1 3/3/1\n",
+        );
+        assert_eq!(objects.children().len(), 1);
+        let ray = Ray::new(Point3::new(0.2, 0.3, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = objects
+            .hit(&ray, 0.001, f32::INFINITY)
+            .expect("missed the triangle");
+        assert!(
+            (rec.p - Point3::new(0.2, 0.3, 0.0)).norm() < 1e-5,
+            "{:?}",
+            rec.p
+        );
+        assert!(
+            (rec.normal - Vec3::new(0.0, 0.6, 0.8)).norm() < 1e-5,
+            "{:?}",
+            rec.normal
+        );
+        assert!(
+            (rec.u - 0.6).abs() < 1e-5 && (rec.v - 0.65).abs() < 1e-5,
+            "{} {}",
+            rec.u,
+            rec.v
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to load")]
+    fn rejects_malformed_files() {
+        load_source("malformed", "v 0 0 0\nv 1 zero 0\nv 0 1 0\nf 1 2 3\n");
+    }
+}
@@ -3,8 +3,22 @@ use crate::vec3::Vec3;
 pub mod cosine;
 pub mod hittable;
 pub mod mixture;
+pub mod sphere;
 
 pub trait Pdf {
     fn value(&self, direction: Vec3) -> f32;
     fn generate(&self) -> Vec3;
 }
+
+/// Multiple importance sampling weight for a sample drawn from the strategy
+/// with density `f`, when another strategy could have drawn it with density
+/// `g`.
+pub fn power_heuristic(f: f32, g: f32) -> f32 {
+    let f2 = f * f;
+    let g2 = g * g;
+    if f2 + g2 == 0.0 {
+        0.0
+    } else {
+        f2 / (f2 + g2)
+    }
+}
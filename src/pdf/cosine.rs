@@ -1,14 +1,12 @@
 use std::f32;
 
-use rand::Rng;
-
+use crate::sampler;
 use crate::{onb::Onb, vec3::Vec3};
 
 use super::Pdf;
 
 fn random_cosine_direction() -> Vec3 {
-    let r1 = rand::thread_rng().gen_range(0.0f32..1.0);
-    let r2 = rand::thread_rng().gen_range(0.0f32..1.0);
+    let [r1, r2] = sampler::next_2d();
     let z = (1.0 - r2).sqrt();
 
     let phi = 2.0 * f32::consts::PI * r1;
@@ -1,7 +1,6 @@
 use std::sync::Arc;
 
-use rand::Rng;
-
+use crate::sampler;
 use crate::vec3::Vec3;
 
 use super::Pdf;
@@ -22,7 +21,7 @@ impl Pdf for MixturePdf {
     }
 
     fn generate(&self) -> Vec3 {
-        if rand::thread_rng().gen_bool(0.5) {
+        if sampler::next_1d() < 0.5 {
             self.p[0].generate()
         } else {
             self.p[1].generate()
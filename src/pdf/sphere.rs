@@ -0,0 +1,19 @@
+use std::f32;
+
+use crate::random_unit_vector;
+use crate::vec3::Vec3;
+
+use super::Pdf;
+
+/// Uniform over all directions.
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: Vec3) -> f32 {
+        1.0 / (4.0 * f32::consts::PI)
+    }
+
+    fn generate(&self) -> Vec3 {
+        random_unit_vector()
+    }
+}
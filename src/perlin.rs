@@ -2,6 +2,7 @@ use itertools::Itertools;
 use rand::Rng;
 
 use crate::random_vec_range;
+use crate::rng;
 use crate::vec3::Vec3;
 use crate::Point3;
 
@@ -24,7 +25,7 @@ fn perlin_generate_perm() -> Box<[isize]> {
 
 fn permute(p: &mut [isize]) {
     for i in (1..p.len()).rev() {
-        let target = rand::thread_rng().gen_range(0..i);
+        let target = rng::rng().gen_range(0..i);
         p.swap(i, target);
     }
 }
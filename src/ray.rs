@@ -1,9 +1,12 @@
 use crate::Vec3;
 
+#[derive(Clone, Copy)]
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
     pub time: f32,
+    /// Componentwise reciprocal of `direction`, for bounding box tests.
+    pub inv_direction: Vec3,
 }
 
 impl Ray {
@@ -12,6 +15,7 @@ impl Ray {
             origin,
             direction,
             time,
+            inv_direction: Vec3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z),
         }
     }
 
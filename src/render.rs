@@ -0,0 +1,374 @@
+//! Passes of samples over the whole image, shared by offline and live
+//! rendering.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crossbeam_channel::Sender;
+use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::aov::{AovPixel, Aovs, Hit, MaterialIds};
+use crate::camera::Camera;
+use crate::color;
+use crate::film::{Film, TileQueue};
+use crate::filter::Filter;
+use crate::hittable::Hittable;
+use crate::integrator::Integrator;
+use crate::rng;
+use crate::sampler::{self, Sampler};
+use crate::tile::Tile;
+use crate::{Color, Point3};
+
+/// The samples taken through one tile of the image in a single pass,
+/// splatted over the pixels the filter reaches.
+pub struct RenderedTile {
+    /// Where the tile comes in `Renderer::tiles`.
+    pub index: usize,
+    pub tile: Tile,
+    /// Samples traced through each pixel of the tile, a row at a time.
+    pub samples: Vec<usize>,
+    /// Sums of the luminance of each pixel's samples and of its square,
+    /// from which the film estimates the pixel's variance.
+    pub luminance: Vec<f64>,
+    pub luminance_sq: Vec<f64>,
+    /// The tile grown by the filter's reach, which the splats cover.
+    pub bounds: Tile,
+    /// Filter-weighted radiance and total weight for each pixel of
+    /// `bounds`, a row at a time.
+    pub sums: Vec<Color>,
+    pub weights: Vec<f32>,
+}
+
+pub struct Renderer<'a> {
+    pub camera: Camera,
+    pub world: &'a dyn Hittable,
+    pub lights: &'a dyn Hittable,
+    pub background: Color,
+    pub integrator: &'a dyn Integrator,
+    pub filter: Filter,
+    pub width: usize,
+    pub height: usize,
+    /// The tiles each pass covers the image with, in the order they start.
+    pub tiles: Vec<Tile>,
+    /// Seeds every sample's random numbers; see `rng`.
+    pub seed: u64,
+    pub sampler: Arc<dyn Sampler>,
+}
+
+impl Renderer<'_> {
+    /// Traces samples `samples` of every pixel, sending each tile to `tx` as
+    /// soon as it is done. Tiles finish in no particular order; a
+    /// `TileQueue` puts them back in order. Tiles that haven't started by
+    /// the time `cancel` is set are never sent.
+    pub fn pass(
+        &self,
+        samples: Range<usize>,
+        progress: ProgressBar,
+        cancel: &AtomicBool,
+        tx: Sender<RenderedTile>,
+    ) {
+        self.pass_pixels(|_, _| samples.clone(), progress, cancel, tx);
+    }
+
+    /// Like `pass`, but traces samples `samples(x, y)` of pixel `(x, y)`.
+    /// Tiles that need none are still sent, empty, so the queue isn't left
+    /// waiting for them.
+    pub fn pass_pixels(
+        &self,
+        samples: impl Fn(usize, usize) -> Range<usize> + Sync,
+        progress: ProgressBar,
+        cancel: &AtomicBool,
+        tx: Sender<RenderedTile>,
+    ) {
+        // Every thread takes the next tile in line when it frees up, so
+        // tiles start in order where a parallel iterator would split the
+        // list into chunks.
+        let next = AtomicUsize::new(0);
+        (0..rayon::current_num_threads())
+            .into_par_iter()
+            .for_each_with(tx, |tx, _| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(tile) = self.tiles.get(index) else {
+                    return;
+                };
+                let samples = tile
+                    .pixels()
+                    .map(|(x, y)| samples(x, y))
+                    .collect::<Vec<_>>();
+                if !cancel.load(Ordering::Relaxed) {
+                    sampler::install(&self.sampler);
+                    // The receiver only hangs up when it no longer wants
+                    // tiles.
+                    let _ = tx.send(self.tile(index, tile, &samples));
+                }
+                progress.inc(1);
+            });
+        progress.finish();
+    }
+
+    /// Traces samples `samples(x, y)` of pixel `(x, y)` and adds them to
+    /// `film` in tile order, calling `each` after every tile that lands.
+    /// The pass runs on the calling thread, so inside `ThreadPool::install`
+    /// it uses that pool.
+    pub fn accumulate(
+        &self,
+        film: &mut Film,
+        samples: impl Fn(usize, usize) -> Range<usize> + Sync,
+        progress: ProgressBar,
+        mut each: impl FnMut(&Film) + Send,
+    ) {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                let mut queue = TileQueue::new(self.tiles.len());
+                for tile in rx {
+                    if queue.push(film, tile) > 0 {
+                        each(film);
+                    }
+                }
+            });
+            self.pass_pixels(samples, progress, &AtomicBool::new(false), tx);
+        });
+    }
+
+    /// Traces samples `samples[i]` through the `i`th pixel of `tile`, the
+    /// `index`th of the pass.
+    fn tile(&self, index: usize, tile: &Tile, samples: &[Range<usize>]) -> RenderedTile {
+        let bounds = tile.expand(self.filter.reach(), self.width, self.height);
+        let mut sums = vec![Color::zeros(); bounds.width() * bounds.height()];
+        let mut weights = vec![0.0; sums.len()];
+        let mut luminance = vec![0.0; samples.len()];
+        let mut luminance_sq = vec![0.0; samples.len()];
+        for (i, (x, y)) in tile.pixels().enumerate() {
+            let pixel = (y * self.width + x) as u64;
+            for sample in samples[i].clone() {
+                rng::seed_sample(self.seed, pixel, sample as u64);
+                sampler::start_sample(pixel, sample as u64);
+                let [dx, dy] = sampler::next_2d();
+                let (sx, sy) = (x as f32 + dx, y as f32 + dy);
+                // Raster y grows downwards but the camera's t grows upwards.
+                let r = self
+                    .camera
+                    .get_ray(sx / self.width as f32, 1.0 - sy / self.height as f32);
+                let radiance = self
+                    .integrator
+                    .li(&r, self.world, self.lights, self.background);
+                // A NaN or infinite sample would poison the whole pixel.
+                let radiance: Color = radiance.map(|c| if c.is_finite() { c } else { 0.0 }).into();
+                let l = color::luminance(&radiance) as f64;
+                luminance[i] += l;
+                luminance_sq[i] += l * l;
+
+                // Rounding can carry `x + dx` up to the next pixel, so keep
+                // the splat inside the bounds it was sized for.
+                let clip = |footprint: Range<usize>, bounds: &Range<usize>| {
+                    footprint.start.max(bounds.start)..footprint.end.min(bounds.end)
+                };
+                let columns = clip(self.filter.footprint(sx, self.width), &bounds.x);
+                for fy in clip(self.filter.footprint(sy, self.height), &bounds.y) {
+                    let wy = self.filter.evaluate(fy as f32 + 0.5 - sy);
+                    for fx in columns.clone() {
+                        let weight = wy * self.filter.evaluate(fx as f32 + 0.5 - sx);
+                        let i = (fy - bounds.y.start) * bounds.width() + fx - bounds.x.start;
+                        sums[i] += radiance * weight;
+                        weights[i] += weight;
+                    }
+                }
+            }
+        }
+        RenderedTile {
+            index,
+            tile: tile.clone(),
+            samples: samples.iter().map(Range::len).collect(),
+            luminance,
+            luminance_sq,
+            bounds,
+            sums,
+            weights,
+        }
+    }
+
+    /// Records what samples `0..samples` of every pixel first hit, tracing
+    /// the same camera rays as the render so the buffers line up with it.
+    pub fn trace_aovs(
+        &self,
+        materials: &MaterialIds,
+        samples: usize,
+        progress: ProgressBar,
+    ) -> Aovs {
+        let (time0, time1) = self.camera.shutter();
+        let raster = |p: Point3| {
+            self.camera
+                .project(p)
+                .map(|[s, t]| [s * self.width as f32, (1.0 - t) * self.height as f32])
+        };
+        let rows = (0..self.height)
+            .into_par_iter()
+            .progress_with(progress)
+            .map(|y| {
+                sampler::install(&self.sampler);
+                let mut pixels = vec![AovPixel::default(); self.width];
+                for (x, aovs) in pixels.iter_mut().enumerate() {
+                    let pixel = (y * self.width + x) as u64;
+                    for sample in 0..samples {
+                        rng::seed_sample(self.seed, pixel, sample as u64);
+                        sampler::start_sample(pixel, sample as u64);
+                        let [dx, dy] = sampler::next_2d();
+                        let r = self.camera.get_ray(
+                            (x as f32 + dx) / self.width as f32,
+                            1.0 - (y as f32 + dy) / self.height as f32,
+                        );
+                        let hit = self.world.hit(&r, 0.001, f32::INFINITY).map(|rec| {
+                            let at = |time: f32| rec.p + rec.velocity * (time - r.time);
+                            let motion = match (raster(at(time0)), raster(at(time1))) {
+                                (Some(from), Some(to)) => [to[0] - from[0], to[1] - from[1]],
+                                _ => [0.0; 2],
+                            };
+                            Hit {
+                                albedo: rec.mat_ptr.albedo(&rec),
+                                normal: rec.normal,
+                                depth: self.camera.depth(rec.p),
+                                position: rec.p,
+                                motion,
+                                object_id: rec.object_id,
+                                material: materials.get(&rec.mat_ptr),
+                            }
+                        });
+                        aovs.add(hit.as_ref());
+                    }
+                }
+                pixels
+            })
+            .collect::<Vec<_>>();
+        Aovs::new(self.width, self.height, rows.concat())
+    }
+}
+
+pub fn progress_bar(len: usize, message: String) -> ProgressBar {
+    ProgressBar::new(len as u64)
+        .with_style(
+            ProgressStyle::default_bar()
+                .progress_chars("█▉▊▋▌▍▎▏ ")
+                .template("[{elapsed_precise}/{duration_precise}] [{wide_bar}] {pos:>7}/{len:7} ({percent:>3}%) {msg}")
+                .unwrap(),
+        )
+        .with_message(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::film::RenderSettings;
+    use crate::filter::FilterKind;
+    use crate::integrator::path::PathIntegrator;
+    use crate::sampler::sobol::SobolSampler;
+    use crate::scenes;
+    use crate::tile::{self, TileOrder};
+
+    const SIZE: usize = 24;
+
+    /// Calls `f` with a renderer for a small Cornell box, filtered wide
+    /// enough that every tile splats into its neighbours.
+    fn with_renderer<T>(f: impl FnOnce(&Renderer) -> T) -> T {
+        let scene = scenes::cornell_box();
+        let integrator = PathIntegrator {
+            max_depth: 8,
+            roulette_depth: 3,
+        };
+        let renderer = Renderer {
+            camera: scene.camera.build(1.0),
+            world: &scene.world,
+            lights: &*scene.lights,
+            background: scene.background,
+            integrator: &integrator,
+            filter: Filter {
+                kind: FilterKind::Mitchell,
+                radius: 2.0,
+            },
+            width: SIZE,
+            height: SIZE,
+            tiles: tile::tiles(SIZE, SIZE, 8, TileOrder::Spiral),
+            seed: 1,
+            sampler: Arc::new(SobolSampler::new(1)),
+        };
+        f(&renderer)
+    }
+
+    fn render(
+        renderer: &Renderer,
+        film: &mut Film,
+        samples: impl Fn(usize, usize) -> Range<usize> + Sync,
+    ) {
+        renderer.accumulate(film, samples, ProgressBar::hidden(), |_| {});
+    }
+
+    #[test]
+    fn thread_count_does_not_change_the_image() {
+        let render_with = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                with_renderer(|renderer| {
+                    let mut film = Film::new(SIZE, SIZE);
+                    render(renderer, &mut film, |_, _| 0..16);
+                    film
+                })
+            })
+        };
+        let serial = render_with(1);
+        for threads in [2, 5] {
+            assert!(
+                render_with(threads) == serial,
+                "{threads} threads differ from 1"
+            );
+        }
+    }
+
+    #[test]
+    fn resumed_render_matches_an_uninterrupted_one() {
+        with_renderer(|renderer| {
+            let mut whole = Film::new(SIZE, SIZE);
+            render(renderer, &mut whole, |_, _| 0..16);
+
+            let path = std::env::temp_dir().join(format!("rt-resume-{}.film", std::process::id()));
+            let mut first = Film::new(SIZE, SIZE);
+            first.set_settings(RenderSettings {
+                scene: "cornell-box".to_string(),
+                seeds: vec![renderer.seed],
+                sampler: "sobol".to_string(),
+                filter: "mitchell".to_string(),
+                filter_radius: renderer.filter.radius,
+                samples_per_pixel: 16,
+            });
+            render(renderer, &mut first, |_, _| 0..8);
+            first.save_checkpoint(&path).unwrap();
+            let mut resumed = Film::load_checkpoint(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(resumed.settings(), first.settings());
+            let taken = |x, y| resumed.samples(x, y);
+            let remaining = (0..SIZE * SIZE)
+                .map(|i| taken(i % SIZE, i / SIZE)..16)
+                .collect::<Vec<_>>();
+            render(renderer, &mut resumed, |x, y| {
+                remaining[y * SIZE + x].clone()
+            });
+
+            // The two halves are summed separately before they meet, so the
+            // sums only agree up to rounding.
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    assert_eq!(resumed.samples(x, y), whole.samples(x, y));
+                    let (a, b) = (resumed.color(x, y), whole.color(x, y));
+                    assert!(
+                        (a - b).abs().max() <= 1e-4 * (1.0 + b.max()),
+                        "pixel ({x}, {y}): {a:?} != {b:?}"
+                    );
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,62 @@
+//! The renderer's source of random numbers.
+//!
+//! Each thread owns one generator. Scene construction seeds it with 0, so
+//! random scenes come out the same whatever `--seed` is, and the renderer
+//! reseeds it before every camera sample from `--seed`, the pixel and the
+//! sample index, so an image doesn't depend on which thread traced which
+//! pixel, and resuming a render at sample `n` draws the same numbers an
+//! uninterrupted render would have.
+
+use std::cell::RefCell;
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::seed_from_u64(0));
+}
+
+/// Restarts this thread's generator from `seed`.
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Restarts this thread's generator for sample `sample` of pixel `pixel`.
+pub fn seed_sample(seed: u64, pixel: u64, sample: u64) {
+    self::seed(mix(seed ^ mix(pixel ^ mix(sample))));
+}
+
+/// A handle to this thread's generator.
+pub fn rng() -> LocalRng {
+    LocalRng
+}
+
+/// Draws from the current thread's generator; see `rng`.
+#[derive(Debug, Clone, Copy)]
+pub struct LocalRng;
+
+impl RngCore for LocalRng {
+    fn next_u32(&mut self) -> u32 {
+        RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        RNG.with(|rng| rng.borrow_mut().fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        RNG.with(|rng| rng.borrow_mut().try_fill_bytes(dest))
+    }
+}
+
+/// The SplitMix64 finalizer, which spreads every input bit over the output.
+pub fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
@@ -0,0 +1,368 @@
+//! Sample values for every random decision along a camera path.
+//!
+//! A `Sampler` is a pure function of the pixel, the sample index and the
+//! dimension, so samples stay reproducible however tiles are scheduled. The
+//! renderer installs one per thread and starts each camera sample with
+//! `start_sample`; the camera then takes the first `CAMERA_DIMENSIONS`
+//! dimensions. Each bounce `start_bounce`s into its own block of
+//! `BOUNCE_DIMENSIONS`, and each `Decision` within it `start_decision`s
+//! into a fixed range of that block, so a decision draws the same
+//! dimensions whether or not the ones before it were made. Anything past
+//! the end of its range, or drawn outside one, falls back to `rng`.
+
+use std::cell::RefCell;
+use std::ops::Range;
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::rng;
+
+pub mod halton;
+pub mod independent;
+pub mod pmj02;
+pub mod sobol;
+pub mod stratified;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SamplerKind {
+    /// Uncorrelated uniform random numbers.
+    Independent,
+    /// One jittered sample per stratum of a grid sized to `--spp`.
+    Stratified,
+    /// The Halton sequence, randomly shifted per pixel.
+    Halton,
+    /// Owen-scrambled Sobol points, shuffled per pixel and dimension.
+    Sobol,
+    /// Progressive multi-jittered (0,2) points.
+    Pmj02,
+}
+
+pub trait Sampler: Send + Sync {
+    /// A value in [0, 1) for dimension `dimension` of sample `index` of
+    /// pixel `pixel`.
+    fn get_1d(&self, pixel: u64, index: u64, dimension: u32) -> f32;
+
+    /// A point in [0, 1)² for the pair of dimensions starting at
+    /// `dimension`.
+    fn get_2d(&self, pixel: u64, index: u64, dimension: u32) -> [f32; 2];
+}
+
+/// Dimensions taken by the camera: pixel position, lens position and time.
+pub const CAMERA_DIMENSIONS: u32 = 5;
+/// Dimensions each bounce may take, the sum of its decisions'.
+pub const BOUNCE_DIMENSIONS: u32 = 10;
+
+/// The random decisions a bounce makes, in the order of their dimensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Which light to sample, at each level of nested lists or meshes that
+    /// hold more than one, and the point on it.
+    Light,
+    /// The choice between mixed densities, then the direction from the one
+    /// chosen, which may itself be a light sample.
+    Bsdf,
+    /// Whether Russian roulette ends the path.
+    Roulette,
+}
+
+impl Decision {
+    /// The dimensions reserved for the decision within a bounce's block.
+    fn dimensions(self) -> Range<u32> {
+        match self {
+            Decision::Light => 0..4,
+            Decision::Bsdf => 4..9,
+            Decision::Roulette => 9..BOUNCE_DIMENSIONS,
+        }
+    }
+}
+
+/// The largest `f32` below 1.
+pub const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+struct Context {
+    sampler: Option<Arc<dyn Sampler>>,
+    pixel: u64,
+    index: u64,
+    /// The first dimension of the current bounce's block.
+    bounce: u32,
+    dimension: u32,
+    end: u32,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Context> = RefCell::new(Context {
+        sampler: None,
+        pixel: 0,
+        index: 0,
+        bounce: 0,
+        dimension: 0,
+        end: 0,
+    });
+}
+
+/// Makes `sampler` this thread's source of samples.
+pub fn install(sampler: &Arc<dyn Sampler>) {
+    CONTEXT.with(|context| context.borrow_mut().sampler = Some(sampler.clone()));
+}
+
+/// Starts sample `index` of pixel `pixel` at the camera's dimensions.
+pub fn start_sample(pixel: u64, index: u64) {
+    CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        context.pixel = pixel;
+        context.index = index;
+        context.dimension = 0;
+        context.end = CAMERA_DIMENSIONS;
+    });
+}
+
+/// Moves on to the block of dimensions reserved for bounce `bounce`. Draws
+/// fall back to `rng` until a decision starts.
+pub fn start_bounce(bounce: usize) {
+    CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        context.bounce = CAMERA_DIMENSIONS + bounce as u32 * BOUNCE_DIMENSIONS;
+        context.dimension = context.bounce;
+        context.end = context.bounce;
+    });
+}
+
+/// Moves on to the dimensions reserved for `decision` in the current
+/// bounce's block.
+pub fn start_decision(decision: Decision) {
+    CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        let dimensions = decision.dimensions();
+        context.dimension = context.bounce + dimensions.start;
+        context.end = context.bounce + dimensions.end;
+    });
+}
+
+/// The next sample dimension, in [0, 1).
+pub fn next_1d() -> f32 {
+    CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        match &context.sampler {
+            Some(sampler) if context.dimension < context.end => {
+                let value = sampler.get_1d(context.pixel, context.index, context.dimension);
+                context.dimension += 1;
+                value
+            }
+            _ => rng::rng().gen_range(0.0..1.0),
+        }
+    })
+}
+
+/// The next two sample dimensions, in [0, 1)².
+pub fn next_2d() -> [f32; 2] {
+    CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        match &context.sampler {
+            Some(sampler) if context.dimension + 2 <= context.end => {
+                let value = sampler.get_2d(context.pixel, context.index, context.dimension);
+                context.dimension += 2;
+                value
+            }
+            _ => {
+                let mut rng = rng::rng();
+                [rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)]
+            }
+        }
+    })
+}
+
+/// Mixes any number of values into one well-distributed hash.
+pub fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0, |hash, &value| rng::mix(hash ^ rng::mix(value)))
+}
+
+/// The top 24 bits of `bits` as a float in [0, 1).
+pub fn to_unit_float(bits: u32) -> f32 {
+    (bits >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Element `index` of a pseudorandom permutation of `0..len` chosen by
+/// `seed`, from Kensler's "Correlated Multi-Jittered Sampling".
+pub fn permute(mut index: u32, len: u32, seed: u32) -> u32 {
+    let mut mask = len.wrapping_sub(1);
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dcb303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e501cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860a3df);
+        index &= mask;
+        index ^= index >> 5;
+        if index < len {
+            return index.wrapping_add(seed) % len;
+        }
+    }
+}
+
+/// Nested uniform (Owen) scrambling of the bits of `x`, using the hash of
+/// Burley's "Practical Hash-based Owen Scrambling".
+pub fn owen_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x.reverse_bits()
+}
+
+/// Maps a point in [0, 1)² to the unit disk with Shirley and Chiu's
+/// concentric mapping, which keeps stratified points stratified.
+pub fn to_unit_disk([u, v]: [f32; 2]) -> [f32; 2] {
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return [0.0, 0.0];
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, std::f32::consts::FRAC_PI_4 * (b / a))
+    } else {
+        (
+            b,
+            std::f32::consts::FRAC_PI_2 - std::f32::consts::FRAC_PI_4 * (a / b),
+        )
+    };
+    [r * theta.cos(), r * theta.sin()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::halton::HaltonSampler;
+    use super::independent::IndependentSampler;
+    use super::pmj02::Pmj02Sampler;
+    use super::sobol::SobolSampler;
+    use super::stratified::StratifiedSampler;
+    use super::*;
+
+    /// Whether `points`, `2^m` of them, have exactly one point in every
+    /// elementary interval of area `2^-m`, of every shape.
+    fn is_stratified(points: &[[f32; 2]]) -> bool {
+        let m = points.len().trailing_zeros();
+        (0..=m).all(|s| {
+            let (columns, rows) = (1 << (m - s), 1 << s);
+            let mut seen = vec![false; points.len()];
+            points.iter().all(|&[x, y]| {
+                let cell = (y * rows as f32) as usize * columns + (x * columns as f32) as usize;
+                !std::mem::replace(&mut seen[cell], true)
+            })
+        })
+    }
+
+    #[test]
+    fn permute_is_a_bijection() {
+        for len in [1, 2, 3, 7, 16, 100, 1000] {
+            for seed in [0, 1, 0xdead_beef] {
+                let mut values = (0..len).map(|i| permute(i, len, seed)).collect::<Vec<_>>();
+                values.sort_unstable();
+                assert_eq!(
+                    values,
+                    (0..len).collect::<Vec<_>>(),
+                    "len {len}, seed {seed}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn power_of_two_prefixes_are_stratified() {
+        let samplers: [(&str, Box<dyn Sampler>); 2] = [
+            ("sobol", Box::new(SobolSampler::new(7))),
+            ("pmj02", Box::new(Pmj02Sampler::new(7))),
+        ];
+        for (name, sampler) in &samplers {
+            for pixel in [0, 1, 12345] {
+                for dimension in [0, 2, CAMERA_DIMENSIONS + 3] {
+                    for m in 0..=10 {
+                        let points = (0..1 << m)
+                            .map(|i| sampler.get_2d(pixel, i, dimension))
+                            .collect::<Vec<_>>();
+                        assert!(
+                            is_stratified(&points),
+                            "{name}: pixel {pixel}, dimension {dimension}, 2^{m} samples"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dimensions_are_independent() {
+        // Pair the first coordinate of two dimensions over 256 samples and
+        // count the cells of a 16x16 grid they land in. Truly independent
+        // values fill about 160; if one dimension were a function of the
+        // other, each column would map to a single row and fill only 16.
+        let samplers: [(&str, Box<dyn Sampler>); 4] = [
+            ("stratified", Box::new(StratifiedSampler::new(7, 256))),
+            ("halton", Box::new(HaltonSampler::new(7))),
+            ("sobol", Box::new(SobolSampler::new(7))),
+            ("pmj02", Box::new(Pmj02Sampler::new(7))),
+        ];
+        for (name, sampler) in &samplers {
+            for (a, b) in [(0, 2), (CAMERA_DIMENSIONS, CAMERA_DIMENSIONS + 2)] {
+                let mut cells = [false; 256];
+                for i in 0..256 {
+                    let x = sampler.get_2d(3, i, a)[0];
+                    let y = sampler.get_2d(3, i, b)[0];
+                    cells[(y * 16.0) as usize * 16 + (x * 16.0) as usize] = true;
+                }
+                let filled = cells.iter().filter(|&&c| c).count();
+                assert!(filled > 100, "{name}: dimensions {a} and {b} fill {filled}");
+            }
+        }
+    }
+
+    #[test]
+    fn values_are_in_the_unit_interval() {
+        let samplers: [(&str, Box<dyn Sampler>); 6] = [
+            ("independent", Box::new(IndependentSampler::new(3))),
+            ("stratified", Box::new(StratifiedSampler::new(3, 16))),
+            (
+                "stratified at 3 spp",
+                Box::new(StratifiedSampler::new(3, 3)),
+            ),
+            ("halton", Box::new(HaltonSampler::new(3))),
+            ("sobol", Box::new(SobolSampler::new(3))),
+            ("pmj02", Box::new(Pmj02Sampler::new(3))),
+        ];
+        let unit = 0.0..1.0;
+        for (name, sampler) in &samplers {
+            for pixel in [0, 99, u32::MAX as u64 + 5] {
+                for index in (0..64).chain([4095, 4096, 70_000]) {
+                    for dimension in 0..CAMERA_DIMENSIONS + 3 * BOUNCE_DIMENSIONS {
+                        let x = sampler.get_1d(pixel, index, dimension);
+                        let [u, v] = sampler.get_2d(pixel, index, dimension);
+                        assert!(
+                            unit.contains(&x) && unit.contains(&u) && unit.contains(&v),
+                            "{name}: {x}, [{u}, {v}] at pixel {pixel}, sample {index}, \
+                             dimension {dimension}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
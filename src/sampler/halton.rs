@@ -0,0 +1,77 @@
+use super::independent::IndependentSampler;
+use super::{hash, permute, Sampler, ONE_MINUS_EPSILON};
+
+/// Dimensions with their own prime base; later ones are independent.
+const MAX_DIMENSIONS: usize = 512;
+
+/// The Halton sequence, with dimension `d` the radical inverse of the sample
+/// index in the `d`th prime base. Each pixel and dimension Owen scrambles
+/// the digits with its own permutations, which keeps neighbouring pixels
+/// from sharing a pattern and breaks up the strong correlation between
+/// consecutive large bases.
+pub struct HaltonSampler {
+    seed: u64,
+    primes: Vec<u64>,
+    fallback: IndependentSampler,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        let mut primes = Vec::with_capacity(MAX_DIMENSIONS);
+        let mut candidate = 2;
+        while primes.len() < MAX_DIMENSIONS {
+            if primes.iter().all(|&p| candidate % p != 0) {
+                primes.push(candidate);
+            }
+            candidate += 1;
+        }
+        Self {
+            seed,
+            primes,
+            fallback: IndependentSampler::new(seed),
+        }
+    }
+}
+
+/// The radical inverse of `index` in `base`, with each digit permuted by a
+/// permutation chosen from `seed` and the digits before it, continued past
+/// the last nonzero digit until it fills an `f32`.
+fn scrambled_radical_inverse(base: u64, mut index: u64, seed: u64) -> f32 {
+    let inverse_base = 1.0 / base as f64;
+    let mut scale = 1.0;
+    let mut result = 0.0;
+    let mut prefix = 0u64;
+    let mut level = 0;
+    while scale > 1.0 / (1u64 << 24) as f64 {
+        let digit = index % base;
+        index /= base;
+        let permuted = permute(
+            digit as u32,
+            base as u32,
+            hash(&[seed, level, prefix]) as u32,
+        );
+        scale *= inverse_base;
+        result += permuted as f64 * scale;
+        prefix = prefix.wrapping_mul(base).wrapping_add(digit);
+        level += 1;
+    }
+    (result as f32).min(ONE_MINUS_EPSILON)
+}
+
+impl Sampler for HaltonSampler {
+    fn get_1d(&self, pixel: u64, index: u64, dimension: u32) -> f32 {
+        let base = match self.primes.get(dimension as usize) {
+            Some(&base) => base,
+            None => return self.fallback.get_1d(pixel, index, dimension),
+        };
+        let seed = hash(&[self.seed, pixel, dimension as u64]);
+        scrambled_radical_inverse(base, index, seed)
+    }
+
+    fn get_2d(&self, pixel: u64, index: u64, dimension: u32) -> [f32; 2] {
+        [
+            self.get_1d(pixel, index, dimension),
+            self.get_1d(pixel, index, dimension + 1),
+        ]
+    }
+}
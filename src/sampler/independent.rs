@@ -0,0 +1,25 @@
+use super::{hash, to_unit_float, Sampler};
+
+/// Hashes every pixel, sample and dimension to its own random value.
+pub struct IndependentSampler {
+    seed: u64,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn get_1d(&self, pixel: u64, index: u64, dimension: u32) -> f32 {
+        to_unit_float(hash(&[self.seed, pixel, index, dimension as u64]) as u32)
+    }
+
+    fn get_2d(&self, pixel: u64, index: u64, dimension: u32) -> [f32; 2] {
+        [
+            self.get_1d(pixel, index, dimension),
+            self.get_1d(pixel, index, dimension + 1),
+        ]
+    }
+}
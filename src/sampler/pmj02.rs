@@ -0,0 +1,190 @@
+use std::sync::OnceLock;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::{hash, owen_scramble, to_unit_float, Sampler, ONE_MINUS_EPSILON};
+
+/// Points in the shared table, and so the longest run of samples before the
+/// sampler starts over on a freshly scrambled copy of it.
+const TABLE_SIZE: usize = 1 << 12;
+
+/// Progressive multi-jittered (0,2) points from Christensen, Kensler and
+/// Kilpatrick's "Progressive Multi-Jittered Sample Sequences". One table is
+/// generated on first use. Each pixel, dimension and pass through the table
+/// reads it in its own order and Owen scrambles the coordinates, so no
+/// dimension is a fixed function of another. Only the table's power-of-two
+/// prefixes are guaranteed to be stratified, so the order only shuffles
+/// samples among those with the same leading bit, and the first `2^m`
+/// samples of a pixel are always the first `2^m` points of the table.
+pub struct Pmj02Sampler {
+    seed: u64,
+    points: &'static [[f32; 2]],
+}
+
+impl Pmj02Sampler {
+    pub fn new(seed: u64) -> Self {
+        static TABLE: OnceLock<Vec<[f32; 2]>> = OnceLock::new();
+        Self {
+            seed,
+            points: TABLE.get_or_init(|| generate(TABLE_SIZE)),
+        }
+    }
+
+    /// The table point for a sample, and the seeds to scramble its
+    /// coordinates with.
+    fn point(&self, pixel: u64, index: u64, dimension: u32) -> ([f32; 2], [u32; 2]) {
+        let a = hash(&[
+            self.seed,
+            pixel,
+            dimension as u64,
+            index / TABLE_SIZE as u64,
+        ]);
+        let b = hash(&[a]);
+        let shuffled = shuffle((index % TABLE_SIZE as u64) as u32, a as u32);
+        (self.points[shuffled as usize], [(a >> 32) as u32, b as u32])
+    }
+}
+
+/// Scrambles the bits of `index` below its leading one, which reorders
+/// `2^k..2^(k + 1)` among themselves for every `k`.
+fn shuffle(index: u32, seed: u32) -> u32 {
+    let bits = 31u32.saturating_sub(index.leading_zeros());
+    if bits == 0 {
+        return index;
+    }
+    // The top bits of an Owen scramble depend only on the top bits it was
+    // given, so scrambling the low bits in the top of a word permutes them.
+    let low = index << (32 - bits);
+    let scrambled = owen_scramble(low, seed ^ bits) >> (32 - bits);
+    1 << bits | scrambled
+}
+
+/// The fixed-point bits of a coordinate in [0, 1).
+fn to_bits(x: f32) -> u32 {
+    (x as f64 * (1u64 << 32) as f64) as u32
+}
+
+impl Sampler for Pmj02Sampler {
+    fn get_1d(&self, pixel: u64, index: u64, dimension: u32) -> f32 {
+        let ([x, _], [seed_x, _]) = self.point(pixel, index, dimension);
+        to_unit_float(owen_scramble(to_bits(x), seed_x))
+    }
+
+    fn get_2d(&self, pixel: u64, index: u64, dimension: u32) -> [f32; 2] {
+        let ([x, y], [seed_x, seed_y]) = self.point(pixel, index, dimension);
+        [
+            to_unit_float(owen_scramble(to_bits(x), seed_x)),
+            to_unit_float(owen_scramble(to_bits(y), seed_y)),
+        ]
+    }
+}
+
+/// Which elementary intervals of the unit square already hold a point: one
+/// grid per shape from `n`x1 strata to 1x`n`.
+struct Strata {
+    n: usize,
+    occupied: Vec<Vec<bool>>,
+}
+
+impl Strata {
+    /// Marks the intervals of `points` for a sequence of `n` points.
+    fn new(points: &[[f32; 2]], n: usize) -> Self {
+        let shapes = n.trailing_zeros() as usize + 1;
+        let mut strata = Self {
+            n,
+            occupied: vec![vec![false; n]; shapes],
+        };
+        for &point in points {
+            strata.mark(point);
+        }
+        strata
+    }
+
+    fn cells(&self, [x, y]: [f32; 2]) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.occupied.len()).map(move |shape| {
+            let x_divisions = self.n >> shape;
+            let y_divisions = 1 << shape;
+            let column = (x * x_divisions as f32) as usize;
+            let row = (y * y_divisions as f32) as usize;
+            (shape, row * x_divisions + column)
+        })
+    }
+
+    fn mark(&mut self, point: [f32; 2]) {
+        for (shape, cell) in self.cells(point).collect::<Vec<_>>() {
+            self.occupied[shape][cell] = true;
+        }
+    }
+
+    fn is_occupied(&self, point: [f32; 2]) -> bool {
+        self.cells(point)
+            .any(|(shape, cell)| self.occupied[shape][cell])
+    }
+}
+
+/// Generates `len` points, a power of four, by repeatedly filling the empty
+/// subquadrants of the cells the existing points stake out.
+fn generate(len: usize) -> Vec<[f32; 2]> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut points = vec![[rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)]];
+    while points.len() < len {
+        // Each of the N points owns one cell of a √N x √N grid. First add
+        // a point in the diagonally opposite subquadrant of every cell...
+        let n = points.len();
+        let cells = (n as f32).sqrt() as usize;
+        let mut strata = Strata::new(&points, 2 * n);
+        for i in 0..n {
+            let (cell, [half_x, half_y]) = locate(points[i], cells);
+            let point = place(&mut rng, &mut strata, cell, [1 - half_x, 1 - half_y], cells);
+            points.push(point);
+        }
+        // ...then fill the two remaining subquadrants.
+        let mut strata = Strata::new(&points, 4 * n);
+        let mut second = Vec::with_capacity(n);
+        for i in 0..n {
+            let (cell, [half_x, half_y]) = locate(points[i], cells);
+            let (first, other) = if rng.gen_bool(0.5) {
+                ([1 - half_x, half_y], [half_x, 1 - half_y])
+            } else {
+                ([half_x, 1 - half_y], [1 - half_x, half_y])
+            };
+            let point = place(&mut rng, &mut strata, cell, first, cells);
+            points.push(point);
+            second.push(place(&mut rng, &mut strata, cell, other, cells));
+        }
+        points.extend(second);
+    }
+    points
+}
+
+/// The grid cell a point is in, and which half of it along each axis.
+fn locate([x, y]: [f32; 2], cells: usize) -> ([usize; 2], [usize; 2]) {
+    let (fx, fy) = (x * cells as f32, y * cells as f32);
+    let cell = [fx as usize, fy as usize];
+    let half = [
+        ((fx - cell[0] as f32) * 2.0) as usize,
+        ((fy - cell[1] as f32) * 2.0) as usize,
+    ];
+    (cell, half)
+}
+
+/// Draws random points in one subquadrant until one lands in elementary
+/// intervals that are all still empty.
+fn place(
+    rng: &mut StdRng,
+    strata: &mut Strata,
+    [cx, cy]: [usize; 2],
+    [hx, hy]: [usize; 2],
+    cells: usize,
+) -> [f32; 2] {
+    loop {
+        let x = (cx as f32 + 0.5 * (hx as f32 + rng.gen_range(0.0..1.0))) / cells as f32;
+        let y = (cy as f32 + 0.5 * (hy as f32 + rng.gen_range(0.0..1.0))) / cells as f32;
+        let point = [x.min(ONE_MINUS_EPSILON), y.min(ONE_MINUS_EPSILON)];
+        if !strata.is_occupied(point) {
+            strata.mark(point);
+            return point;
+        }
+    }
+}
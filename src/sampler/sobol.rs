@@ -0,0 +1,51 @@
+use super::{hash, owen_scramble, to_unit_float, Sampler};
+
+/// The first two dimensions of the Sobol sequence, Owen scrambled. Following
+/// Burley's "Practical Hash-based Owen Scrambling", every 1D or 2D request
+/// gets its own shuffle of the sample order and its own scramble, seeded by
+/// the pixel and dimension, instead of drawing on higher Sobol dimensions.
+pub struct SobolSampler {
+    seed: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    fn seeds(&self, pixel: u64, dimension: u32) -> [u32; 3] {
+        let a = hash(&[self.seed, pixel, dimension as u64]);
+        let b = hash(&[a]);
+        [a as u32, (a >> 32) as u32, b as u32]
+    }
+}
+
+/// The second Sobol dimension, generated by the polynomial x + 1.
+fn sobol_1(index: u32) -> u32 {
+    let mut result = 0;
+    let mut direction = 1 << 31;
+    for bit in 0..32 {
+        if index >> bit & 1 == 1 {
+            result ^= direction;
+        }
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+impl Sampler for SobolSampler {
+    fn get_1d(&self, pixel: u64, index: u64, dimension: u32) -> f32 {
+        let [shuffle, x, _] = self.seeds(pixel, dimension);
+        let index = owen_scramble(index as u32, shuffle);
+        to_unit_float(owen_scramble(index.reverse_bits(), x))
+    }
+
+    fn get_2d(&self, pixel: u64, index: u64, dimension: u32) -> [f32; 2] {
+        let [shuffle, x, y] = self.seeds(pixel, dimension);
+        let index = owen_scramble(index as u32, shuffle);
+        [
+            to_unit_float(owen_scramble(index.reverse_bits(), x)),
+            to_unit_float(owen_scramble(sobol_1(index), y)),
+        ]
+    }
+}
@@ -0,0 +1,61 @@
+use super::independent::IndependentSampler;
+use super::{hash, permute, to_unit_float, Sampler, ONE_MINUS_EPSILON};
+
+/// Splits each dimension into `samples_per_pixel` strata (a square grid of
+/// at least that many for 2D) and visits them in a random order per pixel
+/// and dimension, jittering within each. Samples past the end of the grid,
+/// as when a render is resumed with more samples, are independent.
+pub struct StratifiedSampler {
+    seed: u64,
+    samples_per_pixel: u32,
+    fallback: IndependentSampler,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples_per_pixel: usize) -> Self {
+        Self {
+            seed,
+            samples_per_pixel: samples_per_pixel.max(1) as u32,
+            fallback: IndependentSampler::new(seed),
+        }
+    }
+
+    fn jitter(&self, pixel: u64, index: u64, dimension: u32) -> f32 {
+        to_unit_float(hash(&[self.seed, pixel, index, dimension as u64, 1]) as u32)
+    }
+
+    fn stratum(&self, pixel: u64, index: u64, dimension: u32, strata: u32) -> u32 {
+        let seed = hash(&[self.seed, pixel, dimension as u64]) as u32;
+        permute(index as u32, strata, seed)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn get_1d(&self, pixel: u64, index: u64, dimension: u32) -> f32 {
+        let strata = self.samples_per_pixel;
+        if index >= strata as u64 {
+            return self.fallback.get_1d(pixel, index, dimension);
+        }
+        let stratum = self.stratum(pixel, index, dimension, strata);
+        // The sum can round up to `strata`, as `2.0 + 0.99999994` does.
+        ((stratum as f32 + self.jitter(pixel, index, dimension)) / strata as f32)
+            .min(ONE_MINUS_EPSILON)
+    }
+
+    fn get_2d(&self, pixel: u64, index: u64, dimension: u32) -> [f32; 2] {
+        let side = (self.samples_per_pixel as f32).sqrt().ceil() as u32;
+        let strata = side * side;
+        if index >= strata as u64 {
+            return self.fallback.get_2d(pixel, index, dimension);
+        }
+        let stratum = self.stratum(pixel, index, dimension, strata);
+        let coordinate = |cell: u32, dimension: u32| {
+            ((cell as f32 + self.jitter(pixel, index, dimension)) / side as f32)
+                .min(ONE_MINUS_EPSILON)
+        };
+        [
+            coordinate(stratum % side, dimension),
+            coordinate(stratum / side, dimension + 1),
+        ]
+    }
+}
@@ -0,0 +1,612 @@
+//! Data-driven scene descriptions loaded from TOML files.
+//!
+//! Textures and materials are declared in named tables and referenced by
+//! name from the object tree, so a material can be shared between several
+//! objects exactly like the `Arc`s in `scenes.rs`. Shapes work the same
+//! way: each entry in `[shapes]` is built once into its own BVH, and every
+//! `instance` object places that shared BVH with its own transform.
+//!
+//! `instance` and `transform` objects take the same placement fields: the
+//! object is scaled, rotated about the X, Y and Z axes in that order (in
+//! degrees), translated, and finally multiplied by `matrix`, a row-major
+//! 4x4 affine matrix for anything else such as shear. `animated` objects
+//! take a list of keyframes with the same `translate`, `rotate` and `scale`
+//! fields, interpolated over the camera's shutter for motion blur.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use nalgebra::{Matrix4, UnitQuaternion, Vector3};
+use serde::Deserialize;
+
+use crate::camera::Camera;
+use crate::hittable::aa_rect::{XyRect, XzRect, YzRect};
+use crate::hittable::animated_transform::{AnimatedTransform, Keyframe};
+use crate::hittable::box_obj::BoxObj;
+use crate::hittable::bvh_node::BvhNode;
+use crate::hittable::flip_face::FlipFace;
+use crate::hittable::hittable_list::HittableList;
+use crate::hittable::linear_bvh::LinearBvh;
+use crate::hittable::medium::constant::ConstantMedium;
+use crate::hittable::moving_sphere::MovingSphere;
+use crate::hittable::rotate::RotateY;
+use crate::hittable::sphere::Sphere;
+use crate::hittable::transform::Transform;
+use crate::hittable::translate::Translate;
+use crate::hittable::triangle::Triangle;
+use crate::hittable::Hittable;
+use crate::material::dielectric::Dielectric;
+use crate::material::diffuse_light::DiffuseLight;
+use crate::material::isotropic::Isotropic;
+use crate::material::lambertian::Lambertian;
+use crate::material::metal::Metal;
+use crate::material::Material;
+use crate::texture::checker::CheckerTexture;
+use crate::texture::image::ImageTexture;
+use crate::texture::noise::NoiseTexture;
+use crate::texture::solid_color::SolidColor;
+use crate::texture::Texture;
+use crate::tonemap::ToneMapper;
+use crate::vec3::Vec3;
+use crate::{obj, Color, Point3};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    camera: CameraDesc,
+    #[serde(default)]
+    render: RenderDesc,
+    #[serde(default = "black")]
+    background: Color,
+    #[serde(default)]
+    textures: HashMap<String, TextureDesc>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDesc>,
+    #[serde(default)]
+    shapes: HashMap<String, ShapeDesc>,
+    /// Shapes sampled for next-event estimation. Each should repeat an
+    /// emitter from `objects`; their own materials are never shaded.
+    #[serde(default)]
+    lights: Vec<ObjectDesc>,
+    objects: Vec<ObjectDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDesc {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    #[serde(default = "default_vup")]
+    pub vup: Vec3,
+    pub vfov: f32,
+    #[serde(default)]
+    pub aperture: f32,
+    #[serde(default = "default_focus_dist")]
+    pub focus_dist: f32,
+    #[serde(default)]
+    pub time0: f32,
+    #[serde(default = "default_time1")]
+    pub time1: f32,
+}
+
+impl CameraDesc {
+    pub fn build(&self, aspect_ratio: f32) -> Camera {
+        Camera::new(
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+            self.time0,
+            self.time1,
+        )
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct RenderDesc {
+    pub aspect_ratio: f32,
+    pub image_width: usize,
+    pub samples_per_pixel: usize,
+    pub max_depth: usize,
+    pub tonemap: ToneMapper,
+    pub exposure: f32,
+    pub white_point: Option<f32>,
+}
+
+impl Default for RenderDesc {
+    fn default() -> Self {
+        Self {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            tonemap: ToneMapper::Clamp,
+            exposure: 0.0,
+            white_point: None,
+        }
+    }
+}
+
+/// Either an inline color or the name of an entry in `[textures]`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureRef {
+    Color(Color),
+    Named(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDesc {
+    Solid { color: Color },
+    Checker { even: TextureRef, odd: TextureRef },
+    Noise { scale: f32 },
+    Image { path: PathBuf },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian { albedo: TextureRef },
+    Metal { albedo: Color, fuzz: f32 },
+    Dielectric { ref_idx: f32 },
+    DiffuseLight { emit: TextureRef },
+    Isotropic { albedo: TextureRef },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyframeDesc {
+    time: f32,
+    #[serde(default = "zero")]
+    translate: Vec3,
+    #[serde(default = "zero")]
+    rotate: Vec3,
+    #[serde(default = "one")]
+    scale: Vec3,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShapeDesc {
+    objects: Vec<ObjectDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere {
+        center: Point3,
+        radius: f32,
+        material: String,
+    },
+    MovingSphere {
+        center0: Point3,
+        center1: Point3,
+        time0: f32,
+        time1: f32,
+        radius: f32,
+        material: String,
+    },
+    XyRect {
+        x0: f32,
+        x1: f32,
+        y0: f32,
+        y1: f32,
+        k: f32,
+        material: String,
+    },
+    XzRect {
+        x0: f32,
+        x1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    YzRect {
+        y0: f32,
+        y1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    Box {
+        min: Point3,
+        max: Point3,
+        material: String,
+    },
+    Triangle {
+        vertices: [Point3; 3],
+        normals: Option<[Vec3; 3]>,
+        uvs: Option<[(f32, f32); 3]>,
+        material: String,
+    },
+    /// A Wavefront OBJ file; `material` is used for faces without an MTL
+    /// material.
+    Obj {
+        path: PathBuf,
+        material: String,
+    },
+    FlipFace {
+        object: Box<ObjectDesc>,
+    },
+    Translate {
+        offset: Vec3,
+        object: Box<ObjectDesc>,
+    },
+    RotateY {
+        angle: f32,
+        object: Box<ObjectDesc>,
+    },
+    /// A copy of an entry in `[shapes]`.
+    Instance {
+        shape: String,
+        #[serde(default = "zero")]
+        translate: Vec3,
+        #[serde(default = "zero")]
+        rotate: Vec3,
+        #[serde(default = "one")]
+        scale: Vec3,
+        matrix: Option<[[f32; 4]; 4]>,
+    },
+    Transform {
+        object: Box<ObjectDesc>,
+        #[serde(default = "zero")]
+        translate: Vec3,
+        #[serde(default = "zero")]
+        rotate: Vec3,
+        #[serde(default = "one")]
+        scale: Vec3,
+        matrix: Option<[[f32; 4]; 4]>,
+    },
+    /// Rotations are interpolated along the shortest path, so turns of half
+    /// a revolution or more need intermediate keyframes.
+    Animated {
+        object: Box<ObjectDesc>,
+        keyframes: Vec<KeyframeDesc>,
+    },
+    ConstantMedium {
+        boundary: Box<ObjectDesc>,
+        density: f32,
+        albedo: TextureRef,
+    },
+    Bvh {
+        objects: Vec<ObjectDesc>,
+    },
+    List {
+        objects: Vec<ObjectDesc>,
+    },
+}
+
+fn black() -> Color {
+    Color::zeros()
+}
+
+fn zero() -> Vec3 {
+    Vec3::zeros()
+}
+
+fn one() -> Vec3 {
+    Vec3::new(1.0, 1.0, 1.0)
+}
+
+fn default_vup() -> Vec3 {
+    Vec3::new(0.0, 1.0, 0.0)
+}
+
+fn default_focus_dist() -> f32 {
+    10.0
+}
+
+fn default_time1() -> f32 {
+    1.0
+}
+
+fn placement(
+    translate: Vec3,
+    rotate: Vec3,
+    scale: Vec3,
+    matrix: Option<&[[f32; 4]; 4]>,
+) -> Matrix4<f32> {
+    let matrix = matrix.map_or(Matrix4::identity(), |rows| {
+        Matrix4::from_fn(|r, c| rows[r][c])
+    });
+    matrix
+        * Matrix4::new_translation(&translate)
+        * Matrix4::from_axis_angle(&Vector3::z_axis(), rotate.z.to_radians())
+        * Matrix4::from_axis_angle(&Vector3::y_axis(), rotate.y.to_radians())
+        * Matrix4::from_axis_angle(&Vector3::x_axis(), rotate.x.to_radians())
+        * Matrix4::new_nonuniform_scaling(&scale)
+}
+
+pub struct Scene {
+    pub camera: CameraDesc,
+    pub render: RenderDesc,
+    pub background: Color,
+    pub lights: Arc<dyn Hittable>,
+    pub world: HittableList,
+}
+
+pub fn load(path: &Path) -> Scene {
+    let source = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("failed to read scene file {}: {e}", path.display()));
+    let file: SceneFile = toml::from_str(&source)
+        .unwrap_or_else(|e| panic!("failed to parse scene file {}: {e}", path.display()));
+
+    let mut builder = Builder {
+        base_dir: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+        texture_descs: &file.textures,
+        material_descs: &file.materials,
+        shape_descs: &file.shapes,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        shapes: HashMap::new(),
+        time0: file.camera.time0,
+        time1: file.camera.time1,
+    };
+
+    let mut lights = HittableList::new();
+    for light in &file.lights {
+        lights.add(builder.object(light));
+    }
+    let mut world = HittableList::new();
+    for object in &file.objects {
+        world.add(builder.object(object));
+    }
+
+    Scene {
+        camera: file.camera,
+        render: file.render,
+        background: file.background,
+        lights: Arc::new(lights),
+        world,
+    }
+}
+
+struct Builder<'a> {
+    base_dir: PathBuf,
+    texture_descs: &'a HashMap<String, TextureDesc>,
+    material_descs: &'a HashMap<String, MaterialDesc>,
+    shape_descs: &'a HashMap<String, ShapeDesc>,
+    textures: HashMap<String, Option<Arc<dyn Texture>>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+    shapes: HashMap<String, Option<Arc<dyn Hittable>>>,
+    time0: f32,
+    time1: f32,
+}
+
+impl Builder<'_> {
+    fn texture_ref(&mut self, texture: &TextureRef) -> Arc<dyn Texture> {
+        match texture {
+            TextureRef::Color(color) => Arc::new(SolidColor::new(*color)),
+            TextureRef::Named(name) => self.named_texture(name),
+        }
+    }
+
+    fn named_texture(&mut self, name: &str) -> Arc<dyn Texture> {
+        match self.textures.get(name) {
+            Some(Some(texture)) => return texture.clone(),
+            Some(None) => panic!("texture `{name}` refers to itself"),
+            None => {}
+        }
+        let desc = self
+            .texture_descs
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown texture `{name}`"));
+        // Mark the texture as under construction so cycles are reported
+        // instead of overflowing the stack.
+        self.textures.insert(name.to_string(), None);
+        let texture: Arc<dyn Texture> = match desc {
+            TextureDesc::Solid { color } => Arc::new(SolidColor::new(*color)),
+            TextureDesc::Checker { even, odd } => Arc::new(CheckerTexture::new(
+                self.texture_ref(even),
+                self.texture_ref(odd),
+            )),
+            TextureDesc::Noise { scale } => Arc::new(NoiseTexture::new(*scale)),
+            TextureDesc::Image { path } => {
+                let path = self.base_dir.join(path);
+                let file = File::open(&path)
+                    .unwrap_or_else(|e| panic!("failed to open {}: {e}", path.display()));
+                Arc::new(ImageTexture::new(&mut BufReader::new(file)))
+            }
+        };
+        self.textures
+            .insert(name.to_string(), Some(texture.clone()));
+        texture
+    }
+
+    fn material(&mut self, name: &str) -> Arc<dyn Material> {
+        if let Some(material) = self.materials.get(name) {
+            return material.clone();
+        }
+        let desc = self
+            .material_descs
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown material `{name}`"));
+        let material: Arc<dyn Material> = match desc {
+            MaterialDesc::Lambertian { albedo } => {
+                Arc::new(Lambertian::new(self.texture_ref(albedo)))
+            }
+            MaterialDesc::Metal { albedo, fuzz } => Arc::new(Metal::new(*albedo, *fuzz)),
+            MaterialDesc::Dielectric { ref_idx } => Arc::new(Dielectric::new(*ref_idx)),
+            MaterialDesc::DiffuseLight { emit } => {
+                Arc::new(DiffuseLight::new(self.texture_ref(emit)))
+            }
+            MaterialDesc::Isotropic { albedo } => {
+                Arc::new(Isotropic::new(self.texture_ref(albedo)))
+            }
+        };
+        self.materials.insert(name.to_string(), material.clone());
+        material
+    }
+
+    fn shape(&mut self, name: &str) -> Arc<dyn Hittable> {
+        match self.shapes.get(name) {
+            Some(Some(shape)) => return shape.clone(),
+            Some(None) => panic!("shape `{name}` refers to itself"),
+            None => {}
+        }
+        let desc = self
+            .shape_descs
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown shape `{name}`"));
+        self.shapes.insert(name.to_string(), None);
+        let objects = desc
+            .objects
+            .iter()
+            .map(|o| self.object(o))
+            .collect::<Vec<_>>();
+        let shape: Arc<dyn Hittable> = Arc::new(LinearBvh::new(&objects, self.time0, self.time1));
+        self.shapes.insert(name.to_string(), Some(shape.clone()));
+        shape
+    }
+
+    fn object(&mut self, desc: &ObjectDesc) -> Arc<dyn Hittable> {
+        match desc {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material,
+            } => Arc::new(Sphere::new(*center, *radius, self.material(material))),
+            ObjectDesc::MovingSphere {
+                center0,
+                center1,
+                time0,
+                time1,
+                radius,
+                material,
+            } => Arc::new(MovingSphere::new(
+                *center0,
+                *center1,
+                *time0,
+                *time1,
+                *radius,
+                self.material(material),
+            )),
+            ObjectDesc::XyRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                material,
+            } => Arc::new(XyRect::new(*x0, *x1, *y0, *y1, *k, self.material(material))),
+            ObjectDesc::XzRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Arc::new(XzRect::new(*x0, *x1, *z0, *z1, *k, self.material(material))),
+            ObjectDesc::YzRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                material,
+            } => Arc::new(YzRect::new(*y0, *y1, *z0, *z1, *k, self.material(material))),
+            ObjectDesc::Box { min, max, material } => {
+                Arc::new(BoxObj::new(*min, *max, self.material(material)))
+            }
+            ObjectDesc::Triangle {
+                vertices,
+                normals,
+                uvs,
+                material,
+            } => {
+                let [v0, v1, v2] = *vertices;
+                let mut triangle = Triangle::new(v0, v1, v2, self.material(material));
+                if let Some(normals) = normals {
+                    triangle = triangle.with_normals(*normals);
+                }
+                if let Some(uvs) = uvs {
+                    triangle = triangle.with_uvs(*uvs);
+                }
+                Arc::new(triangle)
+            }
+            ObjectDesc::Obj { path, material } => {
+                let path = self.base_dir.join(path);
+                Arc::new(obj::load(&path, self.material(material)))
+            }
+            ObjectDesc::FlipFace { object } => Arc::new(FlipFace::new(self.boxed(object))),
+            ObjectDesc::Translate { offset, object } => {
+                Arc::new(Translate::new(self.boxed(object), *offset))
+            }
+            ObjectDesc::RotateY { angle, object } => {
+                Arc::new(RotateY::new(self.boxed(object), *angle))
+            }
+            ObjectDesc::Instance {
+                shape,
+                translate,
+                rotate,
+                scale,
+                matrix,
+            } => Arc::new(Transform::new(
+                self.shape(shape),
+                placement(*translate, *rotate, *scale, matrix.as_ref()),
+            )),
+            ObjectDesc::Transform {
+                object,
+                translate,
+                rotate,
+                scale,
+                matrix,
+            } => Arc::new(Transform::new(
+                self.object(object),
+                placement(*translate, *rotate, *scale, matrix.as_ref()),
+            )),
+            ObjectDesc::Animated { object, keyframes } => {
+                let keyframes = keyframes
+                    .iter()
+                    .map(|k| Keyframe {
+                        time: k.time,
+                        translation: k.translate,
+                        rotation: UnitQuaternion::from_euler_angles(
+                            k.rotate.x.to_radians(),
+                            k.rotate.y.to_radians(),
+                            k.rotate.z.to_radians(),
+                        ),
+                        scale: k.scale,
+                    })
+                    .collect();
+                Arc::new(AnimatedTransform::new(self.object(object), keyframes))
+            }
+            ObjectDesc::ConstantMedium {
+                boundary,
+                density,
+                albedo,
+            } => Arc::new(ConstantMedium::new(
+                self.object(boundary),
+                *density,
+                self.texture_ref(albedo),
+            )),
+            ObjectDesc::Bvh { objects } => {
+                let objects = objects.iter().map(|o| self.object(o)).collect::<Vec<_>>();
+                Arc::new(BvhNode::new(&objects, self.time0, self.time1))
+            }
+            ObjectDesc::List { objects } => {
+                let mut list = HittableList::new();
+                for object in objects {
+                    list.add(self.object(object));
+                }
+                Arc::new(list)
+            }
+        }
+    }
+
+    /// The transform wrappers own their child, so hand them a boxed handle.
+    fn boxed(&mut self, desc: &ObjectDesc) -> Box<dyn Hittable> {
+        Box::new(self.object(desc))
+    }
+}
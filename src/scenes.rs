@@ -9,23 +9,118 @@ use crate::hittable::box_obj::BoxObj;
 use crate::hittable::bvh_node::BvhNode;
 use crate::hittable::flip_face::FlipFace;
 use crate::hittable::hittable_list::HittableList;
+use crate::hittable::linear_bvh::LinearBvh;
 use crate::hittable::medium::constant::ConstantMedium;
 use crate::hittable::moving_sphere::MovingSphere;
 use crate::hittable::rotate::RotateY;
 use crate::hittable::sphere::Sphere;
+use crate::hittable::transform::Transform;
 use crate::hittable::translate::Translate;
+use crate::hittable::triangle_mesh::TriangleMesh;
 use crate::hittable::Hittable;
 use crate::material::dielectric::Dielectric;
 use crate::material::diffuse_light::DiffuseLight;
 use crate::material::lambertian::Lambertian;
 use crate::material::metal::Metal;
+use crate::material::Material;
+use crate::rng;
+use crate::scene_file::{CameraDesc, RenderDesc, Scene};
 use crate::texture::checker::CheckerTexture;
 use crate::texture::image::ImageTexture;
 use crate::texture::noise::NoiseTexture;
 use crate::vec3::Vec3;
 use crate::{random_vec, random_vec_range, Color, Point3};
 
-pub fn random_scene() -> Box<dyn Hittable> {
+use nalgebra::Matrix4;
+
+/// A built-in scene, selected on the command line by `name`.
+#[derive(Debug)]
+pub struct SceneInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub build: fn() -> Scene,
+}
+
+pub const SCENES: &[SceneInfo] = &[
+    SceneInfo {
+        name: "random-scene",
+        description: "The cover of Ray Tracing in One Weekend, with bouncing spheres",
+        build: random_scene,
+    },
+    SceneInfo {
+        name: "two-spheres",
+        description: "Two checkered spheres",
+        build: two_spheres,
+    },
+    SceneInfo {
+        name: "two-perlin-spheres",
+        description: "Two marbled Perlin noise spheres",
+        build: two_perlin_spheres,
+    },
+    SceneInfo {
+        name: "earth",
+        description: "An image-mapped globe",
+        build: earth,
+    },
+    SceneInfo {
+        name: "simple-light",
+        description: "Perlin spheres lit by a rectangle and a sphere light",
+        build: simple_light,
+    },
+    SceneInfo {
+        name: "cornell-box",
+        description: "The Cornell box with an aluminium block and a glass sphere",
+        build: cornell_box,
+    },
+    SceneInfo {
+        name: "cornell-smoke",
+        description: "The Cornell box with two blocks of smoke",
+        build: cornell_smoke,
+    },
+    SceneInfo {
+        name: "final-scene",
+        description: "The cover of Ray Tracing: The Next Week",
+        build: final_scene,
+    },
+    SceneInfo {
+        name: "forest",
+        description: "Ten thousand instances of one tree",
+        build: forest,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static SceneInfo> {
+    SCENES.iter().find(|scene| scene.name == name)
+}
+
+fn camera(lookfrom: Point3, lookat: Point3, vfov: f32) -> CameraDesc {
+    CameraDesc {
+        lookfrom,
+        lookat,
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        vfov,
+        aperture: 0.0,
+        focus_dist: 10.0,
+        time0: 0.0,
+        time1: 1.0,
+    }
+}
+
+/// Material for the shapes in a light list, which are only sampled and
+/// never shaded.
+fn hidden() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new_color(Color::zeros()))
+}
+
+fn light_list(lights: Vec<Arc<dyn Hittable>>) -> Arc<dyn Hittable> {
+    let mut list = HittableList::new();
+    for light in lights {
+        list.add(light);
+    }
+    Arc::new(list)
+}
+
+pub fn random_scene() -> Scene {
     let mut world = HittableList::new();
 
     let checker = Arc::new(CheckerTexture::new_color(
@@ -41,11 +136,11 @@ pub fn random_scene() -> Box<dyn Hittable> {
 
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = rand::thread_rng().gen_range(0.0..1.0);
+            let choose_mat = rng::rng().gen_range(0.0..1.0);
             let center = Point3::new(
-                a as f32 + 0.9 * rand::thread_rng().gen_range(0.0..1.0),
+                a as f32 + 0.9 * rng::rng().gen_range(0.0..1.0),
                 0.2,
-                b as f32 + 0.9 * rand::thread_rng().gen_range(0.0..1.0),
+                b as f32 + 0.9 * rng::rng().gen_range(0.0..1.0),
             );
 
             if (center - Vec3::new(4.0, 0.2, 0.0)).magnitude() > 0.9 {
@@ -53,8 +148,7 @@ pub fn random_scene() -> Box<dyn Hittable> {
                     // diffuse
                     let albedo: Vec3 = random_vec().component_mul(&random_vec()).into();
                     let sphere_material = Arc::new(Lambertian::new_color(albedo));
-                    let center2 =
-                        center + Vec3::new(0.0, rand::thread_rng().gen_range(0.0..0.5), 0.0);
+                    let center2 = center + Vec3::new(0.0, rng::rng().gen_range(0.0..0.5), 0.0);
                     world.add(Arc::new(MovingSphere::new(
                         center,
                         center2,
@@ -66,7 +160,7 @@ pub fn random_scene() -> Box<dyn Hittable> {
                 } else if choose_mat < 0.95 {
                     // metal
                     let albedo = random_vec_range(0.5, 1.0);
-                    let fuzz = rand::thread_rng().gen_range(0.0..0.5);
+                    let fuzz = rng::rng().gen_range(0.0..0.5);
                     let sphere_material = Arc::new(Metal::new(albedo, fuzz));
                     world.add(Arc::new(Sphere::new(center, 0.2, sphere_material)));
                 } else {
@@ -99,10 +193,20 @@ pub fn random_scene() -> Box<dyn Hittable> {
         material3,
     )));
 
-    Box::new(world)
+    Scene {
+        camera: camera(
+            Point3::new(13.0, 2.0, 3.0),
+            Point3::new(0.0, 0.0, 0.0),
+            20.0,
+        ),
+        render: RenderDesc::default(),
+        background: Color::new(0.7, 0.8, 1.0),
+        lights: Arc::new(HittableList::new()),
+        world,
+    }
 }
 
-pub fn two_spheres() -> Box<dyn Hittable> {
+pub fn two_spheres() -> Scene {
     let mut objects = HittableList::new();
 
     let checker = Arc::new(CheckerTexture::new_color(
@@ -122,10 +226,20 @@ pub fn two_spheres() -> Box<dyn Hittable> {
         checker_mat,
     )));
 
-    Box::new(objects)
+    Scene {
+        camera: camera(
+            Point3::new(13.0, 2.0, 3.0),
+            Point3::new(0.0, 0.0, 0.0),
+            20.0,
+        ),
+        render: RenderDesc::default(),
+        background: Color::new(0.7, 0.8, 1.0),
+        lights: Arc::new(HittableList::new()),
+        world: objects,
+    }
 }
 
-pub fn two_perlin_spheres() -> Box<dyn Hittable> {
+pub fn two_perlin_spheres() -> Scene {
     let mut objects = HittableList::new();
 
     let pertext = Arc::new(NoiseTexture::new(4.0));
@@ -141,10 +255,20 @@ pub fn two_perlin_spheres() -> Box<dyn Hittable> {
         perlin_mat,
     )));
 
-    Box::new(objects)
+    Scene {
+        camera: camera(
+            Point3::new(13.0, 2.0, 3.0),
+            Point3::new(0.0, 0.0, 0.0),
+            20.0,
+        ),
+        render: RenderDesc::default(),
+        background: Color::new(0.7, 0.8, 1.0),
+        lights: Arc::new(HittableList::new()),
+        world: objects,
+    }
 }
 
-pub fn earth() -> Box<dyn Hittable> {
+pub fn earth() -> Scene {
     let mut objects = HittableList::new();
 
     const EARTH_DATA: &[u8] = include_bytes!("texture/image/earthmap.jpg");
@@ -157,10 +281,20 @@ pub fn earth() -> Box<dyn Hittable> {
         earth_mat,
     )));
 
-    Box::new(objects)
+    Scene {
+        camera: camera(
+            Point3::new(13.0, 2.0, 3.0),
+            Point3::new(0.0, 0.0, 0.0),
+            20.0,
+        ),
+        render: RenderDesc::default(),
+        background: Color::new(0.7, 0.8, 1.0),
+        lights: Arc::new(HittableList::new()),
+        world: objects,
+    }
 }
 
-pub fn simple_light() -> Box<dyn Hittable> {
+pub fn simple_light() -> Scene {
     let mut objects = HittableList::new();
 
     let pertext = Arc::new(NoiseTexture::new(4.0));
@@ -192,10 +326,26 @@ pub fn simple_light() -> Box<dyn Hittable> {
         difflight,
     )));
 
-    Box::new(objects)
+    Scene {
+        camera: camera(
+            Point3::new(26.0, 3.0, 6.0),
+            Point3::new(0.0, 2.0, 0.0),
+            20.0,
+        ),
+        render: RenderDesc {
+            samples_per_pixel: 400,
+            ..Default::default()
+        },
+        background: Color::zeros(),
+        lights: light_list(vec![
+            Arc::new(XyRect::new(3.0, 5.0, 1.0, 3.0, -2.0, hidden())),
+            Arc::new(Sphere::new(Point3::new(0.0, 7.0, 0.0), 2.0, hidden())),
+        ]),
+        world: objects,
+    }
 }
 
-pub fn cornell_box() -> Box<dyn Hittable> {
+pub fn cornell_box() -> Scene {
     let mut objects = HittableList::new();
 
     let red = Arc::new(Lambertian::new_color(Color::new(0.65, 0.05, 0.05)));
@@ -242,10 +392,32 @@ pub fn cornell_box() -> Box<dyn Hittable> {
         glass,
     )));
 
-    Box::new(objects)
+    Scene {
+        camera: camera(
+            Point3::new(278.0, 278.0, -800.0),
+            Point3::new(278.0, 278.0, 0.0),
+            40.0,
+        ),
+        render: RenderDesc {
+            aspect_ratio: 1.0,
+            image_width: 600,
+            samples_per_pixel: 1000,
+            ..Default::default()
+        },
+        background: Color::zeros(),
+        lights: light_list(vec![Arc::new(XzRect::new(
+            213.0,
+            343.0,
+            227.0,
+            332.0,
+            554.0,
+            hidden(),
+        ))]),
+        world: objects,
+    }
 }
 
-pub fn cornell_smoke() -> Box<dyn Hittable> {
+pub fn cornell_smoke() -> Scene {
     let mut objects = HittableList::new();
 
     let red = Arc::new(Lambertian::new_color(Color::new(0.65, 0.05, 0.05)));
@@ -307,11 +479,37 @@ pub fn cornell_smoke() -> Box<dyn Hittable> {
         Color::new(1.0, 1.0, 1.0),
     )));
 
-    Box::new(objects)
+    Scene {
+        camera: camera(
+            Point3::new(278.0, 278.0, -800.0),
+            Point3::new(278.0, 278.0, 0.0),
+            40.0,
+        ),
+        render: RenderDesc {
+            aspect_ratio: 1.0,
+            image_width: 600,
+            samples_per_pixel: 200,
+            ..Default::default()
+        },
+        background: Color::zeros(),
+        lights: light_list(vec![Arc::new(XzRect::new(
+            113.0,
+            443.0,
+            127.0,
+            432.0,
+            554.0,
+            hidden(),
+        ))]),
+        world: objects,
+    }
 }
 
-pub fn final_scene() -> Box<dyn Hittable> {
-    let mut boxes1 = HittableList::new();
+pub fn final_scene() -> Scene {
+    let mut objects = HittableList::new();
+    // Perlin noise draws its tables from the scene generator too. Building
+    // it before anything random gives the same pattern that loading
+    // scenes/final_scene.toml does.
+    let pertext = Arc::new(NoiseTexture::new(0.1));
     let ground = Arc::new(Lambertian::new_color(Color::new(0.48, 0.83, 0.53)));
 
     const boxes_per_side: usize = 20;
@@ -323,19 +521,16 @@ pub fn final_scene() -> Box<dyn Hittable> {
             let z0 = -1000.0 + j as f32 * w;
             let y0 = 0.0;
             let x1 = x0 + w;
-            let y1 = rand::thread_rng().gen_range(1.0..101.0);
+            let y1 = rng::rng().gen_range(1.0..101.0);
             let z1 = z0 + w;
 
-            boxes1.add(Arc::new(BoxObj::new(
+            objects.add(Arc::new(BoxObj::new(
                 Point3::new(x0, y0, z0),
                 Point3::new(x1, y1, z1),
                 ground.clone(),
             )));
         });
 
-    let mut objects = HittableList::new();
-    objects.add(Arc::new(BvhNode::new(boxes1.children(), 0.0, 1.0)));
-
     let light = Arc::new(DiffuseLight::new_color(Color::new(7.0, 7.0, 7.0)));
     objects.add(Arc::new(FlipFace::new(Box::new(XzRect::new(
         123.0, 423.0, 147.0, 412.0, 554.0, light,
@@ -392,7 +587,6 @@ pub fn final_scene() -> Box<dyn Hittable> {
         100.0,
         emat,
     )));
-    let pertext = Arc::new(NoiseTexture::new(0.1));
     objects.add(Arc::new(Sphere::new(
         Point3::new(220.0, 280.0, 300.0),
         80.0,
@@ -418,5 +612,172 @@ pub fn final_scene() -> Box<dyn Hittable> {
         Vec3::new(-100.0, 270.0, 395.0),
     )));
 
-    Box::new(objects)
+    Scene {
+        camera: camera(
+            Point3::new(478.0, 278.0, -600.0),
+            Point3::new(278.0, 278.0, 0.0),
+            40.0,
+        ),
+        render: RenderDesc {
+            aspect_ratio: 1.0,
+            image_width: 800,
+            samples_per_pixel: 10000,
+            ..Default::default()
+        },
+        background: Color::zeros(),
+        lights: light_list(vec![Arc::new(XzRect::new(
+            123.0,
+            423.0,
+            147.0,
+            412.0,
+            554.0,
+            hidden(),
+        ))]),
+        world: objects,
+    }
+}
+
+/// Ten thousand instances of one tree, all sharing a single bottom-level BVH.
+pub fn forest() -> Scene {
+    let mut objects = HittableList::new();
+
+    let ground = Arc::new(Lambertian::new_color(Color::new(0.35, 0.5, 0.2)));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground,
+    )));
+
+    let sun = Arc::new(DiffuseLight::new_color(Color::new(15.0, 14.0, 12.0)));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(-200.0, 400.0, 100.0),
+        60.0,
+        sun,
+    )));
+
+    let bark = Arc::new(Lambertian::new_color(Color::new(0.4, 0.25, 0.1)));
+    let leaves = Arc::new(Lambertian::new_color(Color::new(0.1, 0.4, 0.15)));
+    let trunk: Arc<dyn Hittable> = Arc::new(BoxObj::new(
+        Point3::new(-0.1, 0.0, -0.1),
+        Point3::new(0.1, 0.6, 0.1),
+        bark,
+    ));
+    let canopy: Arc<dyn Hittable> = Arc::new(TriangleMesh::new(
+        vec![
+            Point3::new(-0.5, 0.4, -0.5),
+            Point3::new(0.5, 0.4, -0.5),
+            Point3::new(0.5, 0.4, 0.5),
+            Point3::new(-0.5, 0.4, 0.5),
+            Point3::new(0.0, 2.0, 0.0),
+        ],
+        None,
+        None,
+        vec![
+            [0, 1, 4],
+            [1, 2, 4],
+            [2, 3, 4],
+            [3, 0, 4],
+            [0, 2, 1],
+            [0, 3, 2],
+        ],
+        leaves,
+    ));
+    let tree: Arc<dyn Hittable> = Arc::new(LinearBvh::new(&[trunk, canopy], 0.0, 1.0));
+
+    const trees_per_side: usize = 100;
+    let spacing = 3.0;
+    let mut rng = rng::rng();
+    (0..trees_per_side)
+        .cartesian_product(0..trees_per_side)
+        .for_each(|(i, j)| {
+            let x = (i as f32 - trees_per_side as f32 / 2.0) * spacing + rng.gen_range(-1.0..1.0);
+            let z = (j as f32 - trees_per_side as f32 / 2.0) * spacing + rng.gen_range(-1.0..1.0);
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let scale = rng.gen_range(0.7..1.5);
+            let transform = Matrix4::new_translation(&nalgebra::Vector3::new(x, 0.0, z))
+                * Matrix4::from_euler_angles(0.0, angle, 0.0)
+                * Matrix4::new_scaling(scale);
+            objects.add(Arc::new(Transform::new(tree.clone(), transform)));
+        });
+
+    Scene {
+        camera: camera(
+            Point3::new(0.0, 25.0, -170.0),
+            Point3::new(0.0, 0.0, 0.0),
+            30.0,
+        ),
+        render: RenderDesc::default(),
+        background: Color::new(0.7, 0.8, 1.0),
+        lights: light_list(vec![Arc::new(Sphere::new(
+            Point3::new(-200.0, 400.0, 100.0),
+            60.0,
+            hidden(),
+        ))]),
+        world: objects,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use indicatif::ProgressBar;
+
+    use super::*;
+    use crate::film::Film;
+    use crate::filter::{Filter, FilterKind};
+    use crate::integrator::path::PathIntegrator;
+    use crate::render::Renderer;
+    use crate::sampler::sobol::SobolSampler;
+    use crate::scene_file;
+    use crate::tile::{self, TileOrder};
+
+    /// Renders a small image of `scene` after building it with `build`,
+    /// which runs on a freshly seeded generator as it does in `main`.
+    fn render(build: impl FnOnce() -> Scene) -> Film {
+        rng::seed(0);
+        let scene = build();
+        let (width, height) = (48, 32);
+        let world = LinearBvh::new(scene.world.children(), 0.0, 1.0);
+        let integrator = PathIntegrator {
+            max_depth: scene.render.max_depth,
+            roulette_depth: 3,
+        };
+        let renderer = Renderer {
+            camera: scene.camera.build(width as f32 / height as f32),
+            world: &world,
+            lights: &*scene.lights,
+            background: scene.background,
+            integrator: &integrator,
+            filter: Filter {
+                kind: FilterKind::Box,
+                radius: 0.5,
+            },
+            width,
+            height,
+            tiles: tile::tiles(width, height, 16, TileOrder::Scanline),
+            seed: 0,
+            sampler: Arc::new(SobolSampler::new(0)),
+        };
+        let mut film = Film::new(width, height);
+        renderer.accumulate(&mut film, |_, _| 0..4, ProgressBar::hidden(), |_| {});
+        film
+    }
+
+    #[test]
+    fn scene_files_match_the_built_in_scenes() {
+        for scene in SCENES {
+            // The forest is built around a mesh generated in code.
+            if scene.name == "forest" {
+                continue;
+            }
+            let path = Path::new("scenes").join(format!("{}.toml", scene.name.replace('-', "_")));
+            assert!(
+                render(scene.build) == render(|| scene_file::load(&path)),
+                "{} doesn't render like --scene {}",
+                path.display(),
+                scene.name
+            );
+        }
+    }
 }
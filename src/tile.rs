@@ -0,0 +1,134 @@
+//! Splitting the image into the square tiles render passes are scheduled
+//! by, and the orders they are handed out in.
+
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TileOrder {
+    /// Left to right along each row of tiles, from the top.
+    Scanline,
+    /// Outwards from the center of the image, so the subject tends to
+    /// appear first.
+    Spiral,
+    /// Along a Hilbert curve, which keeps consecutive tiles next to each
+    /// other.
+    Hilbert,
+}
+
+/// A rectangle of pixels, with row 0 at the top as in `Film`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tile {
+    pub x: Range<usize>,
+    pub y: Range<usize>,
+}
+
+impl Tile {
+    pub fn width(&self) -> usize {
+        self.x.len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.y.len()
+    }
+
+    /// The tile grown by `margin` pixels on every side, clipped to an image
+    /// of `width` by `height`.
+    pub fn expand(&self, margin: usize, width: usize, height: usize) -> Tile {
+        Tile {
+            x: self.x.start.saturating_sub(margin)..(self.x.end + margin).min(width),
+            y: self.y.start.saturating_sub(margin)..(self.y.end + margin).min(height),
+        }
+    }
+
+    /// The pixels of the tile, a row at a time.
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.y
+            .clone()
+            .flat_map(move |y| self.x.clone().map(move |x| (x, y)))
+    }
+}
+
+/// Covers an image of `width` by `height` with tiles `size` pixels square,
+/// clipped where they overhang the right and bottom edges, and lists them
+/// in `order`.
+pub fn tiles(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<Tile> {
+    let columns = width.div_ceil(size);
+    let rows = height.div_ceil(size);
+    let cells = match order {
+        TileOrder::Scanline => (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .collect(),
+        TileOrder::Spiral => spiral(columns, rows),
+        TileOrder::Hilbert => hilbert(columns, rows),
+    };
+    cells
+        .into_iter()
+        .map(|(column, row)| Tile {
+            x: column * size..((column + 1) * size).min(width),
+            y: row * size..((row + 1) * size).min(height),
+        })
+        .collect()
+}
+
+/// The cells of a `columns` by `rows` grid in a square spiral from the
+/// center: one step right, one down, two left, two up, three right and so
+/// on, skipping the steps that fall outside the grid.
+fn spiral(columns: usize, rows: usize) -> Vec<(usize, usize)> {
+    const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let total = columns * rows;
+    let mut cells = Vec::with_capacity(total);
+    let (mut x, mut y) = ((columns as isize - 1) / 2, (rows as isize - 1) / 2);
+    let mut run = 1;
+    let mut turn = 0;
+    while cells.len() < total {
+        // Each run length is walked twice before it grows.
+        for _ in 0..2 {
+            let (dx, dy) = DIRECTIONS[turn % 4];
+            for _ in 0..run {
+                if (0..columns as isize).contains(&x) && (0..rows as isize).contains(&y) {
+                    cells.push((x as usize, y as usize));
+                }
+                x += dx;
+                y += dy;
+            }
+            turn += 1;
+        }
+        run += 1;
+    }
+    cells
+}
+
+/// The cells of a `columns` by `rows` grid in the order a Hilbert curve
+/// over the smallest power-of-two square holding the grid visits them.
+fn hilbert(columns: usize, rows: usize) -> Vec<(usize, usize)> {
+    let n = columns.max(rows).next_power_of_two();
+    (0..n * n)
+        .map(|d| hilbert_cell(n, d))
+        .filter(|&(x, y)| x < columns && y < rows)
+        .collect()
+}
+
+/// The cell at distance `d` along the Hilbert curve through an `n` by `n`
+/// grid, where `n` is a power of two.
+fn hilbert_cell(n: usize, mut d: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    let mut s = 1;
+    while s < n {
+        let rx = 1 & (d / 2);
+        let ry = 1 & (d ^ rx);
+        // Rotate the quadrant so the curve's ends line up with its
+        // neighbours'.
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        d /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
@@ -0,0 +1,123 @@
+//! Display transforms from linear radiance to 8-bit sRGB.
+
+use image::Rgb;
+use nalgebra::{Matrix3, Vector3};
+use serde::Deserialize;
+
+use crate::color::{linear_to_srgb, luminance};
+use crate::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ToneMapper {
+    /// Clip every channel at 1.
+    Clamp,
+    /// Reinhard's L / (1 + L) on luminance.
+    Reinhard,
+    /// Reinhard with a white point that maps to exactly 1.
+    ReinhardExtended,
+    /// Stephen Hill's fit of the ACES reference and output transforms.
+    Aces,
+    /// Troy Sobotka's AgX, via its common polynomial approximation.
+    Agx,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayTransform {
+    pub tonemap: ToneMapper,
+    /// Scales radiance by 2^exposure before tone mapping.
+    pub exposure: f32,
+    /// Luminance `ReinhardExtended` maps to white, after exposure. `None`
+    /// uses the brightest pixel of the image.
+    pub white_point: Option<f32>,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        Self {
+            tonemap: ToneMapper::Clamp,
+            exposure: 0.0,
+            white_point: None,
+        }
+    }
+}
+
+impl DisplayTransform {
+    /// `white_point` must already be resolved for `ReinhardExtended`.
+    pub fn apply(&self, color: &Color, white_point: f32) -> Rgb<u8> {
+        let color = *color * self.exposure.exp2();
+        let mapped = match self.tonemap {
+            ToneMapper::Clamp => color,
+            ToneMapper::Reinhard => scale_luminance(color, |l| l / (1.0 + l)),
+            ToneMapper::ReinhardExtended => {
+                let white2 = white_point * white_point;
+                scale_luminance(color, |l| l * (1.0 + l / white2) / (1.0 + l))
+            }
+            ToneMapper::Aces => aces(color),
+            ToneMapper::Agx => agx(color),
+        };
+        let encode = |c: f32| (255.0 * linear_to_srgb(c.clamp(0.0, 1.0))).round() as u8;
+        Rgb([encode(mapped.x), encode(mapped.y), encode(mapped.z)])
+    }
+}
+
+fn scale_luminance(color: Color, curve: impl Fn(f32) -> f32) -> Color {
+    let l = luminance(&color);
+    if l > 0.0 {
+        color * (curve(l) / l)
+    } else {
+        Color::zeros()
+    }
+}
+
+fn aces(color: Color) -> Color {
+    #[rustfmt::skip]
+    let input = Matrix3::new(
+        0.59719, 0.35458, 0.04823,
+        0.07600, 0.90834, 0.01566,
+        0.02840, 0.13383, 0.83777,
+    );
+    #[rustfmt::skip]
+    let output = Matrix3::new(
+        1.60475, -0.53108, -0.07367,
+        -0.10256, 1.10813, -0.00605,
+        -0.00327, -0.07276, 1.07602,
+    );
+    let v = input * *color;
+    let fitted = v.map(|x| {
+        let a = x * (x + 0.0245786) - 0.000090537;
+        let b = x * (0.983729 * x + 0.432951) + 0.238081;
+        a / b
+    });
+    (output * fitted).into()
+}
+
+fn agx(color: Color) -> Color {
+    const MIN_EV: f32 = -12.47393;
+    const MAX_EV: f32 = 4.026069;
+    #[rustfmt::skip]
+    let inset = Matrix3::new(
+        0.84247906, 0.0784336, 0.07922375,
+        0.04232824, 0.87846864, 0.07916613,
+        0.04237565, 0.0784336, 0.879143,
+    );
+    #[rustfmt::skip]
+    let outset = Matrix3::new(
+        1.196879, -0.09802088, -0.09902974,
+        -0.05289685, 1.1519031, -0.09896118,
+        -0.05297164, -0.09804345, 1.1510737,
+    );
+
+    let v: Vector3<f32> = inset * *color;
+    let v = v.map(|x| {
+        let x = (x.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        // Sixth-order fit of the AgX base contrast curve.
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    });
+    // The curve's output is display-encoded; undo that so the sRGB encode
+    // that follows doesn't apply twice.
+    (outset * v).map(|x| x.max(0.0).powf(2.2)).into()
+}
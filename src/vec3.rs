@@ -1,6 +1,9 @@
 use std::ops::{Add, AddAssign, Deref, DerefMut, Div, Mul, Neg, Sub};
 
-#[derive(Debug, Clone, Copy)]
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(from = "[f32; 3]")]
 pub struct Vec3(nalgebra::Vector3<f32>);
 
 impl Vec3 {
@@ -107,6 +110,12 @@ impl From<nalgebra::Vector3<f32>> for Vec3 {
     }
 }
 
+impl From<[f32; 3]> for Vec3 {
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
 impl PartialEq for Vec3 {
     fn eq(&self, other: &Self) -> bool {
         self.0 == other.0
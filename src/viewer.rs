@@ -0,0 +1,329 @@
+//! The `--live` window, which shows the film refining tile by tile while
+//! the render runs on other threads.
+//!
+//! The camera can be flown around the scene while it renders: WASD moves,
+//! Q and E move down and up, dragging with the left mouse button looks
+//! around, the scroll wheel zooms, `[` and `]` shrink and grow the
+//! aperture, and `-` and `=` pull the focus distance in and out. Every move
+//! starts the render over, and P prints the camera as a scene file
+//! `[camera]` table.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::TryRecvError;
+use indicatif::ProgressBar;
+use nalgebra::{Rotation3, Unit};
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{Texture, WindowCanvas};
+
+use crate::film::{Film, TileQueue};
+use crate::render::Renderer;
+use crate::scene_file::CameraDesc;
+use crate::tonemap::DisplayTransform;
+use crate::vec3::Vec3;
+
+const TITLE: &str = "Ray Tracing in One Weekend";
+
+/// Passes double in size up to this many samples, so the window keeps
+/// refreshing even late in a long render.
+const MAX_PASS_SAMPLES: usize = 32;
+
+/// Shortest time between uploads of the film to the window.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Fraction of the distance to `lookat` covered by one movement key press.
+const MOVE_STEP: f32 = 0.05;
+/// Radians turned per pixel of mouse movement.
+const LOOK_SPEED: f32 = 0.005;
+
+pub struct SdlState {
+    canvas: WindowCanvas,
+    event_pump: sdl2::EventPump,
+}
+
+impl SdlState {
+    pub fn new(width: usize, height: usize) -> Self {
+        let sdl = sdl2::init().unwrap();
+        let video = sdl.video().unwrap();
+        let canvas = video
+            .window(TITLE, width as u32, height as u32)
+            .build()
+            .unwrap()
+            .into_canvas()
+            .present_vsync()
+            .build()
+            .unwrap();
+        let event_pump = sdl.event_pump().unwrap();
+        Self { canvas, event_pump }
+    }
+}
+
+/// Camera parameters the viewer's controls edit.
+struct FlyCamera {
+    desc: CameraDesc,
+    aspect_ratio: f32,
+}
+
+impl FlyCamera {
+    /// Applies `event` to the camera, returning whether it moved.
+    fn handle(&mut self, event: &Event) -> bool {
+        let desc = &mut self.desc;
+        let forward = desc.lookat - desc.lookfrom;
+        let right = Vec3::from(forward.cross(&desc.vup)).normalize();
+        let up = desc.vup.normalize();
+        let step = MOVE_STEP * forward.norm();
+        match *event {
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } => {
+                let offset = match key {
+                    Keycode::W => forward.normalize() * step,
+                    Keycode::S => forward.normalize() * -step,
+                    Keycode::D => right * step,
+                    Keycode::A => right * -step,
+                    Keycode::E => up * step,
+                    Keycode::Q => up * -step,
+                    Keycode::LeftBracket => {
+                        desc.aperture = (desc.aperture - 0.01 * desc.focus_dist).max(0.0);
+                        return true;
+                    }
+                    Keycode::RightBracket => {
+                        desc.aperture += 0.01 * desc.focus_dist;
+                        return true;
+                    }
+                    Keycode::Minus => {
+                        desc.focus_dist /= 1.1;
+                        return true;
+                    }
+                    Keycode::Equals => {
+                        desc.focus_dist *= 1.1;
+                        return true;
+                    }
+                    _ => return false,
+                };
+                desc.lookfrom += offset;
+                desc.lookat += offset;
+                true
+            }
+            Event::MouseMotion {
+                mousestate,
+                xrel,
+                yrel,
+                ..
+            } if mousestate.left() => {
+                let yaw = Rotation3::from_axis_angle(
+                    &Unit::new_normalize(*up),
+                    -xrel as f32 * LOOK_SPEED,
+                );
+                let pitch = Rotation3::from_axis_angle(
+                    &Unit::new_normalize(*right),
+                    -yrel as f32 * LOOK_SPEED,
+                );
+                let turned = pitch * yaw * *forward;
+                // Looking straight along `vup` would leave the camera with no
+                // sideways direction, so stop just short of it.
+                let turned = if turned.normalize().dot(&up).abs() < 0.99 {
+                    turned
+                } else {
+                    yaw * *forward
+                };
+                desc.lookat = desc.lookfrom + turned.into();
+                true
+            }
+            Event::MouseWheel { y, .. } if y != 0 => {
+                desc.vfov = (desc.vfov - 2.0 * y as f32).clamp(1.0, 150.0);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn print(&self) {
+        let desc = &self.desc;
+        let v = |v: Vec3| format!("[{:?}, {:?}, {:?}]", v.x, v.y, v.z);
+        println!("[camera]");
+        println!("lookfrom = {}", v(desc.lookfrom));
+        println!("lookat = {}", v(desc.lookat));
+        println!("vup = {}", v(desc.vup));
+        println!("vfov = {:?}", desc.vfov);
+        println!("aperture = {:?}", desc.aperture);
+        println!("focus_dist = {:?}", desc.focus_dist);
+    }
+}
+
+enum Outcome {
+    Quit,
+    Moved,
+}
+
+/// Renders `samples_per_pixel` samples into `film` in progressively larger
+/// passes, showing each as it lands, and starts over from `camera`'s new
+/// position whenever it is moved. Closing the window or pressing Escape
+/// cancels the render; Ctrl+S writes the film so far to `output`.
+pub fn run(
+    mut state: SdlState,
+    renderer: &mut Renderer,
+    camera: CameraDesc,
+    aspect_ratio: f32,
+    film: &mut Film,
+    samples_per_pixel: usize,
+    display: &DisplayTransform,
+    output: &Path,
+) {
+    let texture_creator = state.canvas.texture_creator();
+    let mut texture = texture_creator
+        .create_texture_streaming(
+            PixelFormatEnum::RGB24,
+            film.width() as u32,
+            film.height() as u32,
+        )
+        .unwrap();
+
+    let mut camera = FlyCamera {
+        desc: camera,
+        aspect_ratio,
+    };
+    loop {
+        let outcome = render(
+            &mut state,
+            &mut texture,
+            renderer,
+            &mut camera,
+            film,
+            samples_per_pixel,
+            display,
+            output,
+        );
+        match outcome {
+            Outcome::Quit => return,
+            Outcome::Moved => {
+                film.clear();
+                renderer.camera = camera.desc.build(camera.aspect_ratio);
+            }
+        }
+    }
+}
+
+/// Runs one render to completion, showing it until the window is closed or
+/// the camera moves.
+fn render(
+    state: &mut SdlState,
+    texture: &mut Texture,
+    renderer: &Renderer,
+    camera: &mut FlyCamera,
+    film: &mut Film,
+    samples_per_pixel: usize,
+    display: &DisplayTransform,
+    output: &Path,
+) -> Outcome {
+    let cancel = AtomicBool::new(false);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let mut done = 0;
+            while done < samples_per_pixel && !cancel.load(Ordering::Relaxed) {
+                let samples = done
+                    .clamp(1, MAX_PASS_SAMPLES)
+                    .min(samples_per_pixel - done);
+                renderer.pass(
+                    done..done + samples,
+                    ProgressBar::hidden(),
+                    &cancel,
+                    tx.clone(),
+                );
+                done += samples;
+            }
+            // Hang up so the window knows the render has finished.
+            drop(tx);
+        });
+
+        let render_start = Instant::now();
+        let mut completed = 0;
+        let mut queue = TileQueue::new(renderer.tiles.len());
+        let mut tiles_in_pass = 0;
+        let mut rendering = true;
+        let mut dirty = false;
+        let mut last_refresh = Instant::now();
+        let outcome = 'sdl_loop: loop {
+            let mut moved = false;
+            for event in state.event_pump.poll_iter() {
+                match event {
+                    Event::Quit { .. }
+                    | Event::KeyDown {
+                        keycode: Some(Keycode::Escape),
+                        ..
+                    } => break 'sdl_loop Outcome::Quit,
+                    Event::KeyDown {
+                        keycode: Some(Keycode::S),
+                        keymod,
+                        ..
+                    } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                        match film.save(output, display) {
+                            Ok(()) => println!("Saved {completed} spp to {}", output.display()),
+                            Err(e) => eprintln!("failed to write {}: {e}", output.display()),
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::P),
+                        ..
+                    } => camera.print(),
+                    event => moved |= camera.handle(&event),
+                }
+            }
+            if moved {
+                break Outcome::Moved;
+            }
+
+            while rendering {
+                match rx.try_recv() {
+                    Ok(tile) => {
+                        // Live passes sample every pixel alike.
+                        let pass_samples = tile.samples[0];
+                        let added = queue.push(film, tile);
+                        if added == 0 {
+                            continue;
+                        }
+                        dirty = true;
+                        tiles_in_pass += added;
+                        let title = if tiles_in_pass == renderer.tiles.len() {
+                            tiles_in_pass = 0;
+                            completed += pass_samples;
+                            format!("{TITLE} - {completed}/{samples_per_pixel} spp")
+                        } else {
+                            format!(
+                                "{TITLE} - {completed}/{samples_per_pixel} spp, \
+                                 tile {tiles_in_pass}/{} of +{pass_samples}",
+                                renderer.tiles.len()
+                            )
+                        };
+                        state.canvas.window_mut().set_title(&title).unwrap();
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        rendering = false;
+                        println!(
+                            "Rendering took {} seconds",
+                            render_start.elapsed().as_secs_f32()
+                        );
+                    }
+                }
+            }
+
+            if dirty && (!rendering || last_refresh.elapsed() >= REFRESH_INTERVAL) {
+                texture
+                    .update(None, &film.to_rgb_image(display), film.width() * 3)
+                    .unwrap();
+                dirty = false;
+                last_refresh = Instant::now();
+            }
+            state.canvas.copy(texture, None, None).unwrap();
+            state.canvas.present();
+        };
+        cancel.store(true, Ordering::Relaxed);
+        outcome
+    })
+}